@@ -0,0 +1,183 @@
+use anyhow::{anyhow, Result};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use super::{Currency, Kind, Money, TaxableTrade};
+
+/// The crypto spent with the card at one merchant during a month, and the net income that the
+/// purchases realised.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct CardSpending {
+    #[serde(rename = "Month")]
+    pub(crate) month: String,
+
+    #[serde(rename = "Merchant")]
+    pub(crate) merchant: String,
+
+    #[serde(rename = "Currency")]
+    pub(crate) currency: Currency,
+
+    #[serde(rename = "Purchases")]
+    pub(crate) purchases: usize,
+
+    #[serde(rename = "Amount")]
+    pub(crate) amount: Decimal,
+
+    #[serde(rename = "Income")]
+    pub(crate) income: Decimal,
+
+    #[serde(rename = "Cost")]
+    pub(crate) cost: Decimal,
+
+    #[serde(rename = "Net Income")]
+    pub(crate) net_income: Decimal,
+}
+
+impl CardSpending {
+    /// Groups the taxable trades of card payments by month, merchant and currency. Fails if an
+    /// income or cost is not cash in `base_currency`.
+    pub(crate) fn report(taxable_trades: &[TaxableTrade], base_currency: &Currency) -> Result<Vec<CardSpending>> {
+        let mut report: BTreeMap<(String, String, Currency), CardSpending> = BTreeMap::new();
+
+        for t in taxable_trades {
+            let merchant = match &t.kind {
+                Kind::CardPayment { merchant } => merchant,
+                Kind::Exchange => continue,
+            };
+            let in_base_currency = |m: &Money| m.is_cash() && m.currency() == base_currency;
+            let (cost, net_income) =
+                t.sum_cash_amount()
+                    .zip(t.net_income)
+                    .filter(|_| in_base_currency(&t.income) && t.costs.iter().all(in_base_currency))
+                    .ok_or(anyhow!("Could not report card spending {:?} {}: all values must be in the base currency. Try --estimate-prices", t.date, t.currency))?;
+            let month: String = t.date.as_deref().unwrap_or_default().chars().take(7).collect();
+
+            let spending =
+                report.entry((month.clone(), merchant.clone(), t.currency.clone()))
+                    .or_insert_with(|| CardSpending {
+                        month,
+                        merchant: merchant.clone(),
+                        currency: t.currency.clone(),
+                        purchases: 0,
+                        amount: Decimal::ZERO,
+                        income: Decimal::ZERO,
+                        cost: Decimal::ZERO,
+                        net_income: Decimal::ZERO,
+                    });
+            spending.purchases += 1;
+            spending.amount += t.amount;
+            spending.income += t.income.amount();
+            spending.cost += cost;
+            spending.net_income += net_income;
+        }
+
+        Ok(report.into_values().collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::calculator::card_spending::CardSpending;
+    use crate::calculator::{Kind, Money, TaxableTrade};
+    use rust_decimal_macros::dec;
+    use std::error::Error;
+
+    #[test]
+    fn should_group_card_payments_by_merchant_and_month() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let trade = |date: &str, income, cost, kind| TaxableTrade::new(
+            Some(date.to_string()),
+            "EOS".to_string(),
+            dec!(-10),
+            Money::new_cash("SEK".to_string(), income),
+            vec![Money::new_cash("SEK".to_string(), cost)],
+            Some(income + cost)
+        ).with_kind(kind);
+        let taxable_trades = vec![
+            trade("2023-05-06 10:00:00", dec!(200), dec!(-150), Kind::card_payment("Payment to Amazon")),
+            trade("2023-05-20 10:00:00", dec!(100), dec!(-120), Kind::card_payment("Payment to Amazon")),
+            trade("2023-05-21 10:00:00", dec!(100), dec!(-120), Kind::Exchange),
+            trade("2023-06-01 10:00:00", dec!(50), dec!(-40), Kind::card_payment("Payment to Amazon")),
+        ];
+
+        /*
+         * When
+         */
+        let report = CardSpending::report(&taxable_trades, &"SEK".to_string())?;
+
+        /*
+         * Then
+         */
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0], CardSpending {
+            month: "2023-05".to_string(),
+            merchant: "Amazon".to_string(),
+            currency: "EOS".to_string(),
+            purchases: 2,
+            amount: dec!(-20),
+            income: dec!(300),
+            cost: dec!(-270),
+            net_income: dec!(30),
+        });
+        assert_eq!(report[1].month, "2023-06");
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_not_report_a_cost_that_is_not_cash() {
+        /*
+         * Given
+         */
+        let taxable_trades = vec![
+            TaxableTrade::new(
+                Some("2022-05-06 10:00:00".to_string()),
+                "EOS".to_string(),
+                dec!(-10),
+                Money::new_cash("SEK".to_string(), dec!(200)),
+                vec![Money::new_coupon("BTC".to_string(), dec!(-0.001), "2022-01-01 10:00:00".to_string())],
+                None
+            ).with_kind(Kind::card_payment("Payment to Amazon")),
+        ];
+
+        /*
+         * When
+         */
+        let err = CardSpending::report(&taxable_trades, &"SEK".to_string()).unwrap_err();
+
+        /*
+         * Then
+         */
+        assert_eq!(err.to_string(), "Could not report card spending Some(\"2022-05-06 10:00:00\") EOS: all values must be in the base currency. Try --estimate-prices");
+    }
+
+    #[test]
+    fn should_not_report_an_income_in_another_fiat_currency() {
+        /*
+         * Given
+         */
+        let taxable_trades = vec![
+            TaxableTrade::new(
+                Some("2022-05-06 10:00:00".to_string()),
+                "EOS".to_string(),
+                dec!(-10),
+                Money::new_cash("EUR".to_string(), dec!(20)),
+                vec![Money::new_cash("EUR".to_string(), dec!(-15))],
+                Some(dec!(5))
+            ).with_kind(Kind::card_payment("Payment to Amazon")),
+        ];
+
+        /*
+         * When
+         */
+        let err = CardSpending::report(&taxable_trades, &"SEK".to_string()).unwrap_err();
+
+        /*
+         * Then
+         */
+        assert_eq!(err.to_string(), "Could not report card spending Some(\"2022-05-06 10:00:00\") EOS: all values must be in the base currency. Try --estimate-prices");
+    }
+}
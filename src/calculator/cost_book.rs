@@ -1,15 +1,34 @@
 use anyhow::{anyhow, Result};
+use log::{debug, warn};
+use rayon::prelude::*;
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
+use std::collections::BTreeMap;
 use std::ops::{Neg, Sub};
 
-use super::{Currency, TaxableTrade, Money, Trade};
+use super::{lot, Currency, Direction, Holding, Lot, Snapshot, TaxableTrade, Money, Trade};
+
+/// What to do when a sale has not enough costs to deduct from.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum MissingCost {
+    /// Stop the calculation with an error
+    #[default]
+    Abort,
+    /// Report the part of the sale without costs with a zero cost
+    Zero,
+    /// Report 20% of the sale price as the cost of the part of the sale without costs ("schablonmetoden")
+    Schablon,
+}
 
 #[derive(Debug)]
 pub(crate) struct CostBook {
     pub(crate) base_currency: Currency,
     pub(crate) currency: Currency,
     pub(crate) costs: Vec<Cost>,
+    pub(crate) missing_cost: MissingCost,
+    // Total costs added by buys and deducted by sells, by currency
+    cost_in: BTreeMap<Currency, Decimal>,
+    cost_out: BTreeMap<Currency, Decimal>,
 }
 
 impl CostBook {
@@ -18,16 +37,152 @@ impl CostBook {
             base_currency,
             currency,
             costs: vec![],
+            missing_cost: MissingCost::Abort,
+            cost_in: BTreeMap::new(),
+            cost_out: BTreeMap::new(),
+        }
+    }
+
+    pub(crate) fn with_missing_cost(mut self, missing_cost: MissingCost) -> CostBook {
+        self.missing_cost = missing_cost;
+        self
+    }
+
+    /// Replays the books of every currency pair in `trades`.
+    pub(crate) fn replay_all(
+        trades: &[Trade],
+        missing_cost: MissingCost
+    ) -> Result<Vec<(CostBook, Vec<TaxableTrade>)>> {
+        CostBook::replay_all_with(trades, missing_cost, CostBook::replay)
+    }
+
+    /// Replays the books of every currency in `trades` and takes a snapshot after every trade.
+    pub(crate) fn timeline_all(trades: &[Trade], missing_cost: MissingCost) -> Result<Vec<Snapshot>> {
+        let snapshots =
+            CostBook::replay_all_with(trades, missing_cost, CostBook::timeline)?
+                .into_iter()
+                .flat_map(|(_, snapshots)| snapshots)
+                .collect();
+        Ok(snapshots)
+    }
+
+    /// Partitions `trades` by currency pair in one pass, then replays the book of each pair with
+    /// `replay` in parallel. Errors are collected for all pairs before they are returned.
+    fn replay_all_with<T, F>(
+        trades: &[Trade],
+        missing_cost: MissingCost,
+        replay: F
+    ) -> Result<Vec<(CostBook, T)>>
+        where T: Send,
+              F: Fn(&mut CostBook, &[&Trade]) -> Result<T> + Sync
+    {
+        let results: Vec<(CostBook, Result<T>)> =
+            partition_by_pair(trades)
+                .into_par_iter()
+                .map(|((currency, base_currency), trades)| {
+                    let mut book =
+                        CostBook::new(currency, base_currency)
+                            .with_missing_cost(missing_cost);
+                    let result = replay(&mut book, &trades);
+                    (book, result)
+                })
+                .collect();
+
+        let mut books = vec![];
+        let mut errors: Vec<((Currency, Currency), anyhow::Error)> = vec![];
+
+        for (book, result) in results {
+            match result {
+                Ok(result) => books.push((book, result)),
+                Err(e) => errors.push(((book.currency, book.base_currency), e)),
+            }
+        }
+
+        match errors.is_empty() {
+            true => Ok(books),
+            false => Err(CurrencyErrors(errors).into()),
+        }
+    }
+
+    /// Adds the buys and deducts the sells in `trades` in order, then reconciles the book.
+    /// Returns the taxable trades of the sells, or the error of the first sell that failed.
+    pub(crate) fn replay(&mut self, trades: &[&Trade]) -> Result<Vec<TaxableTrade>> {
+        let mut err = Ok(());
+
+        let taxable_trades =
+            trades.iter()
+                .enumerate()
+                .fold(vec![], |mut acc, (i, trade)| {
+                    match trade.direction {
+                        Direction::Buy =>
+                            self.add_buy(trade),
+                        Direction::Sell => {
+                            match self.add_sell(trade) {
+                                Ok(taxable_trade) => acc.push(taxable_trade),
+                                Err(e) if err.is_ok() => err = Err(with_preceding_trades(e, &trades[..i])),
+                                Err(_) => {}
+                            }
+                        }
+                    }
+                    acc
+                });
+
+        err?;
+
+        self.reconcile()?
+            .iter()
+            .for_each(|r| debug!("Costs of {:?} reconciled: {}", self.currency, r));
+
+        debug!("Remaining costs for {:?}:", self.currency);
+        self.costs.iter().for_each(|c| debug!("{:?}", c));
+        debug!("Taxable transactions:");
+        taxable_trades.iter().for_each(|t| debug!("{:?}", t));
+
+        Ok(taxable_trades)
+    }
+
+    /// Adds the buys and deducts the sells in `trades` in order, and takes a snapshot of the
+    /// quantity and cost basis in the book after every trade.
+    pub(crate) fn timeline(&mut self, trades: &[&Trade]) -> Result<Vec<Snapshot>> {
+        trades.iter()
+            .enumerate()
+            .map(|(i, trade)| {
+                match trade.direction {
+                    Direction::Buy =>
+                        self.add_buy(trade),
+                    Direction::Sell => {
+                        self.add_sell(trade).map_err(|e| with_preceding_trades(e, &trades[..i]))?;
+                    }
+                }
+                Ok(self.snapshot(&trade.date))
+            })
+            .collect()
+    }
+
+    fn snapshot(&self, date: &str) -> Snapshot {
+        let (quantity, basis) = quantity_and_basis(&self.costs.iter().collect::<Vec<_>>());
+        Snapshot {
+            date: date.to_string(),
+            currency: self.currency.clone(),
+            quantity,
+            basis,
+            average_cost:
+                basis.filter(|_| !quantity.is_zero())
+                    .map(|b| (b / quantity).round_dp(2)),
         }
     }
 
     pub(crate) fn add_buy(&mut self, trade: &Trade) {
-        match trade.to_money(&self.base_currency) {
+        let cost = trade.to_money(&self.base_currency);
+        *self.cost_in.entry(cost.currency().clone()).or_default() += cost.amount();
+
+        match cost {
             Money::Cash(cash) => {
                 self.find_and_add_cash(
                     trade.is_vault,
                     trade.paid_amount,
-                    cash.amount
+                    cash.amount,
+                    &trade.date
                 );
             }
             cost @ Money::Coupon(_) => {
@@ -36,7 +191,7 @@ impl CostBook {
                         trade.paid_amount,
                         cost,
                         trade.is_vault
-                    )
+                    ).acquired_on(&trade.date)
                 );
             }
         }
@@ -45,12 +200,14 @@ impl CostBook {
     pub(crate) fn add_sell(&mut self, trade: &Trade) -> Result<TaxableTrade> {
         let income = trade.to_money(&self.base_currency);
 
-        let costs =
-            self.find_and_deduct_cost(&income, trade.paid_amount)?
+        let lots: Vec<Lot> =
+            self.find_and_deduct_cost(&income, trade)?
                 .into_iter()
-                .map(|c| c.exchanged)
+                .map(Cost::into_lot)
                 .collect();
 
+        let costs: Vec<Money> = lots.iter().map(|l| l.cost.clone()).collect();
+
         let net_income = income.to_net_income(&costs);
         
         Ok(
@@ -61,23 +218,23 @@ impl CostBook {
                 income,
                 costs,
                 net_income
-            )
+            ).with_lots(lots).with_kind(trade.kind.clone()).with_vault(trade.is_vault)
         )
     }
 
-    fn find_and_add_cash(&mut self, is_vault: bool, paid_amount: Decimal, amount: Decimal) {
+    fn find_and_add_cash(&mut self, is_vault: bool, paid_amount: Decimal, amount: Decimal, date: &str) {
         if let Some(cash_cost) =
             self.costs.iter_mut()
                 .find(|c| c.exchanged.is_cash() && c.is_vault == is_vault)
         {
-            cash_cost.add_cash(paid_amount, amount);
+            cash_cost.add_cash(paid_amount, amount, date);
         } else {
             self.costs.push(
                 Cost::new(
                     paid_amount,
                     Money::new_cash(self.base_currency.clone(), amount),
                     is_vault
-                )
+                ).acquired_on(date)
             );
         }
     }
@@ -87,9 +244,11 @@ impl CostBook {
     /// Likewise, if `income` is `Money::Coupon`, try deduct from the coupons in the `CostBook`.
     /// Only start deducting from the vault if there are no non-vault costs to deduct.
     /// Returns a `Vec<Cost>` which is a list of deducted costs.
-    fn find_and_deduct_cost(&mut self, income: &Money, paid_amount: Decimal) -> Result<Vec<Cost>> {
-        let mut deductor = Deductor::new(&mut self.costs, paid_amount);
-        let deducted =
+    /// If there are not enough costs, `self.missing_cost` decides what to do with the rest.
+    fn find_and_deduct_cost(&mut self, income: &Money, trade: &Trade) -> Result<Vec<Cost>> {
+        let (current, savings) = self.balances();
+        let mut deductor = Deductor::new(&mut self.costs, trade.paid_amount);
+        let mut deducted =
             match income {
                 Money::Cash(_) =>
                     deductor.maybe_deduct(Cost::maybe_deduct_cash_cost)
@@ -105,48 +264,280 @@ impl CostBook {
                         .collect(),
             };
 
-        match deductor.remaining.eq(&dec!(0)) {
-            true => Ok(deducted),
-            false => Err(anyhow!("Not enough costs to deduct from"))
+        for cost in &deducted {
+            *self.cost_out.entry(cost.exchanged.currency().clone()).or_default() += cost.exchanged.amount();
         }
+
+        if deductor.remaining.eq(&dec!(0)) {
+            return Ok(deducted);
+        }
+
+        let err = InsufficientCost {
+            date: trade.date.clone(),
+            currency: trade.paid_currency.clone(),
+            sold: trade.paid_amount.abs(),
+            short: deductor.remaining.abs(),
+            current,
+            savings,
+            preceding: vec![],
+            transfers: vec![],
+        };
+
+        match self.missing_cost {
+            MissingCost::Abort => Err(err.into()),
+            MissingCost::Zero => {
+                warn!("{}\nReporting {} {} with a zero cost.", err, err.short, err.currency);
+                Ok(deducted)
+            }
+            MissingCost::Schablon => {
+                warn!("{}\nReporting {} {} with 20% of the sale price as cost.", err, err.short, err.currency);
+                let schablon = income.scaled(dec!(-0.2) * err.short / err.sold);
+                deducted.push(Cost::new(err.short, schablon, trade.is_vault));
+                Ok(deducted)
+            }
+        }
+    }
+
+    /// Checks that, in every currency, the costs added by buys equal the costs deducted by sells
+    /// plus the costs remaining in the book.
+    pub(crate) fn reconcile(&self) -> Result<Vec<Reconciliation>> {
+        let mut remaining: BTreeMap<Currency, Decimal> = BTreeMap::new();
+        for cost in &self.costs {
+            *remaining.entry(cost.exchanged.currency().clone()).or_default() += cost.exchanged.amount();
+        }
+
+        let reconciliations: Vec<Reconciliation> =
+            self.cost_in.iter()
+                .map(|(currency, cost_in)|
+                    Reconciliation {
+                        currency: currency.clone(),
+                        cost_in: *cost_in,
+                        cost_out: self.cost_out.get(currency).copied().unwrap_or_default(),
+                        remaining: remaining.get(currency).copied().unwrap_or_default(),
+                    }
+                )
+                .collect();
+
+        match reconciliations.iter().find(|r| !r.is_balanced()) {
+            None => Ok(reconciliations),
+            Some(r) => Err(anyhow!("Costs of {} do not reconcile: {}", self.currency, r)),
+        }
+    }
+
+    /// Returns the quantity and cost basis left in the book, per pool.
+    pub(crate) fn holdings(&self) -> Vec<Holding> {
+        [false, true].iter()
+            .filter_map(|is_vault| {
+                let costs: Vec<&Cost> = self.costs.iter().filter(|c| c.is_vault == *is_vault).collect();
+                let (quantity, basis) = quantity_and_basis(&costs);
+                if quantity.is_zero() {
+                    return None;
+                }
+                Some(Holding::new(self.currency.clone(), lot::pool(*is_vault), quantity, basis))
+            })
+            .collect()
+    }
+
+    /// Returns the quantity in the book, outside and inside of the vault.
+    fn balances(&self) -> (Decimal, Decimal) {
+        self.costs.iter()
+            .fold((dec!(0), dec!(0)), |(current, savings), c| {
+                match c.is_vault {
+                    false => (current + c.paid_amount, savings),
+                    true => (current, savings + c.paid_amount),
+                }
+            })
+    }
+}
+
+/// Sums the quantity and the cost basis of `costs`. The basis is `None` when some of the costs
+/// are valued in another coin.
+fn quantity_and_basis(costs: &[&Cost]) -> (Decimal, Option<Decimal>) {
+    let quantity = costs.iter().map(|c| c.paid_amount).sum();
+    let basis =
+        match costs.iter().all(|c| c.exchanged.is_cash()) {
+            true => Some(costs.iter().map(|c| c.exchanged.amount()).sum::<Decimal>().neg()),
+            false => None,
+        };
+    (quantity, basis)
+}
+
+/// Partitions `trades` by the pair of the paid and the exchanged currency in one pass, so that
+/// e.g. EOS bought with SEK and EOS bought with USD are kept in separate books.
+fn partition_by_pair(trades: &[Trade]) -> BTreeMap<(Currency, Currency), Vec<&Trade>> {
+    let mut partitions: BTreeMap<(Currency, Currency), Vec<&Trade>> = BTreeMap::new();
+    for t in trades {
+        partitions.entry((t.paid_currency.clone(), t.exchanged_currency.clone()))
+            .or_default()
+            .push(t);
     }
+    partitions
+}
+
+/// Adds the last trades before the failed sale to an `InsufficientCost`.
+fn with_preceding_trades(err: anyhow::Error, trades: &[&Trade]) -> anyhow::Error {
+    match err.downcast::<InsufficientCost>() {
+        Ok(mut insufficient_cost) => {
+            let mut preceding: Vec<String> =
+                trades.iter()
+                    .rev()
+                    .take(5)
+                    .map(|t| t.to_string())
+                    .collect();
+            preceding.reverse();
+            insufficient_cost.preceding = preceding;
+            insufficient_cost.into()
+        }
+        Err(err) => err,
+    }
+}
+
+/// The costs in one currency that went in and out of a `CostBook`.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Reconciliation {
+    currency: Currency,
+    cost_in: Decimal,
+    cost_out: Decimal,
+    remaining: Decimal,
+}
+
+impl Reconciliation {
+    fn is_balanced(&self) -> bool {
+        self.cost_in == self.cost_out + self.remaining
+    }
+}
+
+impl std::fmt::Display for Reconciliation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} in, {} {} out, {} {} remaining",
+            self.cost_in, self.currency, self.cost_out, self.currency, self.remaining, self.currency
+        )
+    }
+}
+
+/// A sale of more than there are costs to deduct from in the `CostBook`.
+#[derive(Debug, PartialEq)]
+pub(crate) struct InsufficientCost {
+    pub(crate) date: String,
+    pub(crate) currency: Currency,
+    pub(crate) sold: Decimal,
+    pub(crate) short: Decimal,
+    pub(crate) current: Decimal,
+    pub(crate) savings: Decimal,
+    pub(crate) preceding: Vec<String>,
+    // The transfers of the currency in the statement before the sale, which are not trades
+    pub(crate) transfers: Vec<String>,
+}
+
+impl std::fmt::Display for InsufficientCost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Not enough costs to deduct from when selling {} {} on {}: {} {} have no cost.",
+            self.sold, self.currency, self.date, self.short, self.currency
+        )?;
+        writeln!(
+            f,
+            "Balance before the sale: {} {} in Current, {} {} in Savings.",
+            self.current, self.currency, self.savings, self.currency
+        )?;
+        writeln!(
+            f,
+            "Acquisitions that are not in the statement, e.g. transfers in or trades before the first row, have no cost."
+        )?;
+        if !self.preceding.is_empty() {
+            writeln!(f, "Preceding trades in {}:", self.currency)?;
+            for trade in &self.preceding {
+                writeln!(f, "    {}", trade)?;
+            }
+        }
+        if !self.transfers.is_empty() {
+            writeln!(f, "Transfers of {} in the statement before the sale:", self.currency)?;
+            for transfer in &self.transfers {
+                writeln!(f, "    {}", transfer)?;
+            }
+        }
+        write!(f, "Use --missing-cost zero or --missing-cost schablon to report the sale anyway.")
+    }
+}
+
+impl std::error::Error for InsufficientCost {}
 
+/// The errors of the currency pairs, paid and exchanged currency, whose books could not be
+/// replayed.
+#[derive(Debug)]
+pub(crate) struct CurrencyErrors(pub(crate) Vec<((Currency, Currency), anyhow::Error)>);
+
+impl std::fmt::Display for CurrencyErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Could not calculate taxes in {} currency pairs:", self.0.len())?;
+        for ((currency, base_currency), e) in &self.0 {
+            write!(f, "\n{}/{}: {:#}", currency, base_currency, e)?;
+        }
+        Ok(())
+    }
 }
 
+impl std::error::Error for CurrencyErrors {}
+
 
 #[derive(Debug, PartialEq, Clone)]
 pub(crate) struct Cost {
     paid_amount: Decimal,
     exchanged: Money,
     is_vault: bool,
+    acquired: Vec<String>,
 }
 
 impl Cost {
     fn new(paid_amount: Decimal, exchanged: Money, is_vault: bool) -> Cost {
-        Cost{ paid_amount, exchanged, is_vault }
+        Cost{ paid_amount, exchanged, is_vault, acquired: vec![] }
+    }
+
+    fn acquired_on(mut self, date: &str) -> Cost {
+        self.acquired.push(date.to_string());
+        self
+    }
+
+    fn into_lot(self) -> Lot {
+        Lot {
+            acquired: self.acquired,
+            is_vault: self.is_vault,
+            quantity: self.paid_amount,
+            cost: self.exchanged,
+        }
     }
 
     fn maybe_deduct(&mut self, paid_amount: Decimal) -> Option<Cost> {
         if self.paid_amount + paid_amount < dec!(0) {
             None
         } else {
-            let exchanged_amount = self.exchanged.amount() / self.paid_amount * paid_amount.abs();
+            // The last deduction takes everything that is left, including rounding remainders
+            let exchanged_amount =
+                match (self.paid_amount + paid_amount).is_zero() {
+                    true => self.exchanged.amount(),
+                    false => self.exchanged.allocate(paid_amount.abs(), self.paid_amount),
+                };
             let deducted = self.exchanged.deduct(exchanged_amount);
             self.paid_amount += paid_amount;
             Some(
-                Cost::new(
-                    paid_amount.neg(),
-                    deducted,
-                    self.is_vault
-                )
+                Cost {
+                    paid_amount: paid_amount.neg(),
+                    exchanged: deducted,
+                    is_vault: self.is_vault,
+                    acquired: self.acquired.clone(),
+                }
             )
         }
     }
 
-    fn add_cash(&mut self, paid_amount: Decimal, amount: Decimal) {
+    fn add_cash(&mut self, paid_amount: Decimal, amount: Decimal, date: &str) {
         if let Money::Cash(cash) = &mut self.exchanged {
             cash.amount += amount;
             self.paid_amount += paid_amount;
+            self.acquired.push(date.to_string());
         }
     }
 
@@ -187,7 +578,7 @@ struct Deductor<'a> {
 
 impl<'a> Deductor<'a>
 {
-    fn new(costs: &mut Vec<Cost>, paid_amount: Decimal) -> Deductor {
+    fn new(costs: &mut Vec<Cost>, paid_amount: Decimal) -> Deductor<'_> {
         Deductor { costs, remaining: paid_amount, result: vec![] }
     }
 
@@ -229,8 +620,9 @@ impl<'a> Deductor<'a>
 
 #[cfg(test)]
 mod test {
-    use crate::calculator::{CostBook, Money, TaxableTrade, Direction, Trade};
-    use crate::calculator::cost_book::Cost;
+    use crate::calculator::{CostBook, Holding, Lot, Money, Price, PriceBook, TaxableTrade, Direction, Kind, Trade};
+    use crate::calculator::cost_book::{Cost, CurrencyErrors, InsufficientCost, MissingCost};
+    use crate::calculator::price_book::parse_date;
     use rust_decimal_macros::dec;
     use std::error::Error;
 
@@ -251,7 +643,10 @@ mod test {
             exchanged_currency: "SEK".to_string(),
             exchanged_amount: dec!(-20),
             date: "2021-11-11 18:03:13".to_string(),
-            is_vault: true
+            is_vault: true,
+            paid_fee: dec!(0),
+            exchanged_fee: dec!(0),
+            kind: Kind::Exchange
         };
         book.add_buy(&trade);
 
@@ -262,7 +657,10 @@ mod test {
             exchanged_currency: "SEK".to_string(),
             exchanged_amount: dec!(-5080.60),
             date: "2021-12-31 17:54:48".to_string(),
-            is_vault: false
+            is_vault: false,
+            paid_fee: dec!(0),
+            exchanged_fee: dec!(0),
+            kind: Kind::Exchange
         };
         book.add_buy(&trade);
 
@@ -273,7 +671,10 @@ mod test {
             exchanged_currency: "EOS".to_string(),
             exchanged_amount: dec!(-500),
             date: "2022-02-03 10:30:29".to_string(),
-            is_vault: false
+            is_vault: false,
+            paid_fee: dec!(0),
+            exchanged_fee: dec!(0),
+            kind: Kind::Exchange
         };
         book.add_buy(&trade);
 
@@ -284,7 +685,10 @@ mod test {
             exchanged_currency: "EOS".to_string(),
             exchanged_amount: dec!(-62.35),
             date: "2022-02-04 11:01:35".to_string(),
-            is_vault: false
+            is_vault: false,
+            paid_fee: dec!(0),
+            exchanged_fee: dec!(0),
+            kind: Kind::Exchange
         };
         book.add_buy(&trade);
 
@@ -295,22 +699,26 @@ mod test {
         assert_eq!(iter.next(), Some(&Cost{
             paid_amount: dec!(39.94),
             exchanged: Money::new_cash("SEK".to_string(), dec!(-20)),
-            is_vault: true
+            is_vault: true,
+            acquired: vec!["2021-11-11 18:03:13".to_string()]
         }));
         assert_eq!(iter.next(), Some(&Cost{
             paid_amount: dec!(2000),
             exchanged: Money::new_cash("SEK".to_string(), dec!(-5080.6)),
-            is_vault: false
+            is_vault: false,
+            acquired: vec!["2021-12-31 17:54:48".to_string()]
         }));
         assert_eq!(iter.next(), Some(&Cost{
             paid_amount: dec!(200),
             exchanged: Money::new_coupon("EOS".to_string(), dec!(-500), "2022-02-03 10:30:29".to_string()),
-            is_vault: false
+            is_vault: false,
+            acquired: vec!["2022-02-03 10:30:29".to_string()]
         }));
         assert_eq!(iter.next(), Some(&Cost{
             paid_amount: dec!(30.3),
             exchanged: Money::new_coupon("EOS".to_string(), dec!(-62.35), "2022-02-04 11:01:35".to_string()),
-            is_vault: false
+            is_vault: false,
+            acquired: vec!["2022-02-04 11:01:35".to_string()]
         }));
         assert_eq!(iter.next(), None);
 
@@ -325,13 +733,13 @@ mod test {
         let mut book = CostBook::new("DOGE".to_string(), "SEK".to_string());
 
         let coupon = Money::new_coupon("EOS".to_string(), dec!(-500), "2021-02-03 10:30:29".to_string());
-        book.costs.push(Cost::new(dec!(200), coupon, false));
+        book.costs.push(Cost::new(dec!(200), coupon, false).acquired_on("2021-02-03 10:30:29"));
         let coupon = Money::new_coupon("BTC".to_string(), dec!(-0.0000101), "2021-03-04 11:31:30".to_string());
-        book.costs.push(Cost::new(dec!(1000), coupon, false));
+        book.costs.push(Cost::new(dec!(1000), coupon, false).acquired_on("2021-03-04 11:31:30"));
         let cash = Money::new_cash("SEK".to_string(), dec!(-21000));
-        book.costs.push(Cost::new(dec!(10000), cash, false));
+        book.costs.push(Cost::new(dec!(10000), cash, false).acquired_on("2021-01-01 12:00:00").acquired_on("2021-01-02 12:00:00"));
         let cash = Money::new_cash("SEK".to_string(), dec!(-10));
-        book.costs.push(Cost::new(dec!(4.5), cash, true));
+        book.costs.push(Cost::new(dec!(4.5), cash, true).acquired_on("2021-01-03 12:00:00"));
 
         /*
          * When
@@ -343,7 +751,10 @@ mod test {
             exchanged_currency: "SEK".to_string(),
             exchanged_amount: dec!(200.63),
             date: "2022-05-05 05:01:12".to_string(),
-            is_vault: false
+            is_vault: false,
+            paid_fee: dec!(0),
+            exchanged_fee: dec!(0),
+            kind: Kind::Exchange
         };
         let x = book.add_sell(&trade)?;
 
@@ -357,7 +768,14 @@ mod test {
             Money::new_cash("SEK".to_string(), dec!(200.63)),
             vec![Money::new_cash("SEK".to_string(), dec!(-105))],
             Some(dec!(95.63))
-        ));
+        ).with_lots(vec![
+            Lot {
+                acquired: vec!["2021-01-01 12:00:00".to_string(), "2021-01-02 12:00:00".to_string()],
+                is_vault: false,
+                quantity: dec!(50),
+                cost: Money::new_cash("SEK".to_string(), dec!(-105)),
+            }
+        ]));
 
         let trade = Trade {
             direction: Direction::Sell,
@@ -366,7 +784,10 @@ mod test {
             exchanged_currency: "BTC".to_string(),
             exchanged_amount: dec!(0.0000201),
             date: "2022-07-06 06:02:13".to_string(),
-            is_vault: false
+            is_vault: false,
+            paid_fee: dec!(0),
+            exchanged_fee: dec!(0),
+            kind: Kind::Exchange
         };
         let x = book.add_sell(&trade)?;
         assert_eq!(x, TaxableTrade::new(
@@ -376,7 +797,14 @@ mod test {
             Money::new_coupon("BTC".to_string(), dec!(0.0000201), "2022-07-06 06:02:13".to_string()),
             vec![Money::new_coupon("BTC".to_string(), dec!(-0.000000505), "2021-03-04 11:31:30".to_string())],
             None
-        ));
+        ).with_lots(vec![
+            Lot {
+                acquired: vec!["2021-03-04 11:31:30".to_string()],
+                is_vault: false,
+                quantity: dec!(50),
+                cost: Money::new_coupon("BTC".to_string(), dec!(-0.000000505), "2021-03-04 11:31:30".to_string()),
+            }
+        ]));
 
         let trade = Trade {
             direction: Direction::Sell,
@@ -385,7 +813,10 @@ mod test {
             exchanged_currency: "BCH".to_string(),
             exchanged_amount: dec!(325),
             date: "2022-08-07 07:03:14".to_string(),
-            is_vault: false
+            is_vault: false,
+            paid_fee: dec!(0),
+            exchanged_fee: dec!(0),
+            kind: Kind::Exchange
         };
         let x = book.add_sell(&trade)?;
         assert_eq!(x, TaxableTrade::new(
@@ -398,7 +829,167 @@ mod test {
                   , Money::new_cash("SEK".to_string(), dec!(-210))
             ],
             None
-        ));
+        ).with_lots(vec![
+            Lot {
+                acquired: vec!["2021-03-04 11:31:30".to_string()],
+                is_vault: false,
+                quantity: dec!(950),
+                cost: Money::new_coupon("BTC".to_string(), dec!(-0.000009595), "2021-03-04 11:31:30".to_string()),
+            },
+            Lot {
+                acquired: vec!["2021-02-03 10:30:29".to_string()],
+                is_vault: false,
+                quantity: dec!(200),
+                cost: Money::new_coupon("EOS".to_string(), dec!(-500), "2021-02-03 10:30:29".to_string()),
+            },
+            Lot {
+                acquired: vec!["2021-01-01 12:00:00".to_string(), "2021-01-02 12:00:00".to_string()],
+                is_vault: false,
+                quantity: dec!(100),
+                cost: Money::new_cash("SEK".to_string(), dec!(-210)),
+            },
+        ]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_handle_missing_cost() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let trade = Trade {
+            direction: Direction::Sell,
+            paid_currency: "DOGE".to_string(),
+            paid_amount: dec!(-50),
+            exchanged_currency: "SEK".to_string(),
+            exchanged_amount: dec!(200),
+            date: "2022-05-05 05:01:12".to_string(),
+            is_vault: false,
+            paid_fee: dec!(0),
+            exchanged_fee: dec!(0),
+            kind: Kind::Exchange
+        };
+        let book = || {
+            let mut book = CostBook::new("DOGE".to_string(), "SEK".to_string());
+            book.costs.push(Cost::new(dec!(30), Money::new_cash("SEK".to_string(), dec!(-60)), false));
+            book.costs.push(Cost::new(dec!(10), Money::new_cash("SEK".to_string(), dec!(-10)), true));
+            book
+        };
+
+        /*
+         * When
+         */
+        let err = book().add_sell(&trade).unwrap_err();
+
+        /*
+         * Then
+         */
+        assert_eq!(err.downcast_ref::<InsufficientCost>(), Some(&InsufficientCost {
+            date: "2022-05-05 05:01:12".to_string(),
+            currency: "DOGE".to_string(),
+            sold: dec!(50),
+            short: dec!(10),
+            current: dec!(30),
+            savings: dec!(10),
+            preceding: vec![],
+            transfers: vec![],
+        }));
+
+        let x = book().with_missing_cost(MissingCost::Zero).add_sell(&trade)?;
+        assert_eq!(x.net_income, Some(dec!(130)));
+
+        let x = book().with_missing_cost(MissingCost::Schablon).add_sell(&trade)?;
+        assert_eq!(x.net_income, Some(dec!(122)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_list_holdings() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let mut book = CostBook::new("DOGE".to_string(), "SEK".to_string());
+        book.costs.push(Cost::new(dec!(30), Money::new_cash("SEK".to_string(), dec!(-60)), false));
+        book.costs.push(Cost::new(dec!(10), Money::new_cash("SEK".to_string(), dec!(-10)), true));
+        book.costs.push(Cost::new(dec!(5), Money::new_coupon("EOS".to_string(), dec!(-2), "2022-05-05 05:01:12".to_string()), true));
+        let prices = PriceBook::new(vec![Price {
+            currency: "DOGE".to_string(),
+            date: parse_date("2022-12-31 00:00:00").unwrap(),
+            price: dec!(3),
+            source: "".to_string(),
+        }]);
+
+        /*
+         * When
+         */
+        let holdings: Vec<Holding> = book.holdings().into_iter().map(|h| h.valued_at(&prices)).collect();
+
+        /*
+         * Then
+         */
+        assert_eq!(holdings, vec![
+            Holding {
+                currency: "DOGE".to_string(),
+                pool: "Current".to_string(),
+                quantity: dec!(30),
+                average_cost: Some(dec!(2)),
+                basis: Some(dec!(60)),
+                price: Some(dec!(3)),
+                market_value: Some(dec!(90)),
+                unrealized: Some(dec!(30)),
+            },
+            Holding {
+                currency: "DOGE".to_string(),
+                pool: "Savings".to_string(),
+                quantity: dec!(15),
+                average_cost: None,
+                basis: None,
+                price: Some(dec!(3)),
+                market_value: Some(dec!(45)),
+                unrealized: None,
+            },
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_carry_rounding_remainder_to_last_sale() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let mut book = CostBook::new("EOS".to_string(), "SEK".to_string());
+        let trade = |direction, paid_amount, exchanged_amount, date: &str| Trade {
+            direction,
+            paid_currency: "EOS".to_string(),
+            paid_amount,
+            exchanged_currency: "SEK".to_string(),
+            exchanged_amount,
+            date: date.to_string(),
+            is_vault: false,
+            paid_fee: dec!(0),
+            exchanged_fee: dec!(0),
+            kind: Kind::Exchange
+        };
+        book.add_buy(&trade(Direction::Buy, dec!(3), dec!(-100), "2023-01-01 10:00:00"));
+
+        /*
+         * When
+         */
+        let sells: Vec<TaxableTrade> =
+            ["2023-01-02 10:00:00", "2023-01-03 10:00:00", "2023-01-04 10:00:00"].iter()
+                .map(|date| book.add_sell(&trade(Direction::Sell, dec!(-1), dec!(40), date)))
+                .collect::<anyhow::Result<_>>()?;
+
+        /*
+         * Then
+         */
+        let costs: Vec<_> = sells.iter().map(|t| t.sum_cash_amount()).collect();
+        assert_eq!(costs, vec![Some(dec!(-33.33)), Some(dec!(-33.34)), Some(dec!(-33.33))]);
+        assert!(book.costs.is_empty());
+        assert!(book.reconcile().is_ok());
 
         Ok(())
     }
@@ -410,8 +1001,9 @@ mod test {
         let deducted = cost.maybe_deduct(dec!(-500));
         assert_eq!(deducted, Some(Cost{
             paid_amount: dec!(500),
-            exchanged: Money::new_cash("SEK".to_string(), dec!(-1066.6666666666666666666666666)),
-            is_vault: true
+            exchanged: Money::new_cash("SEK".to_string(), dec!(-1066.67)),
+            is_vault: true,
+            acquired: vec![]
         }));
 
         let coupon = Money::new_coupon("EOS".to_string(), dec!(-500), "2021-02-03 10:30:29".to_string());
@@ -420,9 +1012,47 @@ mod test {
         assert_eq!(deducted, Some(Cost{
             paid_amount: dec!(50),
             exchanged: Money::new_coupon("EOS".to_string(), dec!(-125), "2021-02-03 10:30:29".to_string()),
-            is_vault: false
+            is_vault: false,
+            acquired: vec![]
         }));
 
         Ok(())
     }
+
+    #[test]
+    fn should_report_errors_by_currency_pair() {
+        /*
+         * Given
+         */
+        let sell = |exchanged_currency: &str, date: &str| Trade {
+            direction: Direction::Sell,
+            paid_currency: "EOS".to_string(),
+            paid_amount: dec!(-10),
+            exchanged_currency: exchanged_currency.to_string(),
+            exchanged_amount: dec!(100),
+            date: date.to_string(),
+            is_vault: false,
+            paid_fee: dec!(0),
+            exchanged_fee: dec!(0),
+            kind: Kind::Exchange
+        };
+        let trades = vec![
+            sell("SEK", "2023-01-01 10:00:00"),
+            sell("USD", "2023-01-02 10:00:00"),
+        ];
+
+        /*
+         * When
+         */
+        let err = CostBook::replay_all(&trades, MissingCost::Abort).unwrap_err();
+
+        /*
+         * Then
+         */
+        let errors = err.downcast_ref::<CurrencyErrors>().unwrap();
+        let pairs: Vec<(&str, &str)> = errors.0.iter().map(|((currency, base), _)| (currency.as_str(), base.as_str())).collect();
+        assert_eq!(pairs, vec![("EOS", "SEK"), ("EOS", "USD")]);
+        assert!(err.to_string().starts_with("Could not calculate taxes in 2 currency pairs:\nEOS/SEK: Not enough costs"), "{}", err);
+        assert!(err.to_string().contains("\nEOS/USD: Not enough costs"), "{}", err);
+    }
 }
@@ -0,0 +1,204 @@
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use super::{Currency, Direction, Trade};
+
+/// The fees paid in one currency during a year.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct FeeReport {
+    #[serde(rename = "Year")]
+    pub(crate) year: String,
+
+    #[serde(rename = "Currency")]
+    pub(crate) currency: Currency,
+
+    #[serde(rename = "Fees")]
+    pub(crate) fees: Decimal,
+
+    #[serde(rename = "Trades")]
+    pub(crate) trades: usize,
+}
+
+impl FeeReport {
+    /// Sums the fees of `trades` by year and by the currency they were paid in.
+    pub(crate) fn report(trades: &[Trade]) -> Vec<FeeReport> {
+        let mut reports: BTreeMap<(String, Currency), (Decimal, usize)> = BTreeMap::new();
+
+        for trade in trades {
+            let year: String = trade.date.chars().take(4).collect();
+            let fees = [
+                (&trade.paid_currency, trade.paid_fee),
+                (&trade.exchanged_currency, trade.exchanged_fee),
+            ];
+            for (currency, fee) in fees.into_iter().filter(|(_, fee)| !fee.is_zero()) {
+                let (acc_fees, acc_trades) = reports.entry((year.clone(), currency.clone())).or_default();
+                *acc_fees += fee;
+                *acc_trades += 1;
+            }
+        }
+
+        reports.into_iter()
+            .map(|((year, currency), (fees, trades))| FeeReport { year, currency, fees, trades })
+            .collect()
+    }
+}
+
+/// Reports the fees paid in the traded crypto as disposals of their own. The trade keeps the
+/// quantity before the fee, and the value of the fee at the price of the trade is added to its
+/// cost or deducted from its income. The fee is then sold for that value, right after the trade.
+pub(crate) fn with_fee_disposals(trades: Vec<Trade>) -> Vec<Trade> {
+    let mut result = Vec::with_capacity(trades.len());
+
+    for trade in trades {
+        let gross_amount = trade.paid_amount + trade.paid_fee;
+        if trade.paid_fee.is_zero() || gross_amount.is_zero() {
+            result.push(trade);
+            continue;
+        }
+
+        let price = (trade.exchanged_amount / gross_amount).abs();
+        let fee_value = (trade.paid_fee * price).round_dp(2);
+
+        let fee_disposal = Trade {
+            direction: Direction::Sell,
+            paid_currency: trade.paid_currency.clone(),
+            paid_amount: -trade.paid_fee,
+            exchanged_currency: trade.exchanged_currency.clone(),
+            exchanged_amount: fee_value,
+            date: trade.date.clone(),
+            is_vault: trade.is_vault,
+            paid_fee: Decimal::ZERO,
+            exchanged_fee: Decimal::ZERO,
+            kind: trade.kind.clone(),
+        };
+
+        result.push(Trade {
+            paid_amount: gross_amount,
+            exchanged_amount: trade.exchanged_amount - fee_value,
+            paid_fee: Decimal::ZERO,
+            ..trade
+        });
+        result.push(fee_disposal);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::calculator::fee::{with_fee_disposals, FeeReport};
+    use crate::calculator::{Direction, Kind, MissingCost, TaxableTrade, Trade};
+    use crate::reader::RevolutRow2022;
+    use futures::executor::block_on;
+    use rust_decimal_macros::dec;
+    use std::error::Error;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use tempfile::NamedTempFile;
+
+    /// Buys 99 DOGE for 200 SEK with a fee of 1 DOGE, then sells them for 297 SEK with a fee of 3 SEK.
+    fn trades() -> Vec<Trade> {
+        vec![
+            Trade {
+                direction: Direction::Buy,
+                paid_currency: "DOGE".to_string(),
+                paid_amount: dec!(99),
+                exchanged_currency: "SEK".to_string(),
+                exchanged_amount: dec!(-200),
+                date: "2022-01-01 10:00:00".to_string(),
+                is_vault: false,
+                paid_fee: dec!(1),
+                exchanged_fee: dec!(0),
+                kind: Kind::Exchange
+            },
+            Trade {
+                direction: Direction::Sell,
+                paid_currency: "DOGE".to_string(),
+                paid_amount: dec!(-99),
+                exchanged_currency: "SEK".to_string(),
+                exchanged_amount: dec!(297),
+                date: "2022-02-01 10:00:00".to_string(),
+                is_vault: false,
+                paid_fee: dec!(0),
+                exchanged_fee: dec!(3),
+                kind: Kind::Exchange
+            },
+        ]
+    }
+
+    #[test]
+    fn should_sum_fees_by_year_and_currency() {
+        /*
+         * When
+         */
+        let report = FeeReport::report(&trades());
+
+        /*
+         * Then
+         */
+        assert_eq!(report, vec![
+            FeeReport { year: "2022".to_string(), currency: "DOGE".to_string(), fees: dec!(1), trades: 1 },
+            FeeReport { year: "2022".to_string(), currency: "SEK".to_string(), fees: dec!(3), trades: 1 },
+        ]);
+    }
+
+    #[test]
+    fn should_dispose_of_a_fee_paid_in_crypto_on_a_buy() -> Result<(), Box<dyn Error>> {
+        /*
+         * When
+         */
+        let folded = TaxableTrade::taxable_trades_all_currencies(&trades(), MissingCost::Abort)?;
+        let disposals = TaxableTrade::taxable_trades_all_currencies(&with_fee_disposals(trades()), MissingCost::Abort)?;
+
+        /*
+         * Then
+         */
+        // The fee of 1 DOGE is sold for 2 SEK, the price of the buy, and costs 2.02 of the 202 SEK
+        let net_incomes: Vec<_> = disposals.iter().map(|t| (t.amount, t.net_income)).collect();
+        assert_eq!(net_incomes, vec![
+            (dec!(-1), Some(dec!(-0.02))),
+            (dec!(-99), Some(dec!(97.02))),
+        ]);
+        assert_eq!(folded[0].net_income, Some(dec!(97)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_dispose_of_a_fee_paid_in_crypto_on_a_sell_in_a_2022_statement() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "Type,Started Date,Completed Date,Description,Amount,Fee,Currency,Original Amount,Original Currency,Settled Amount,Settled Currency,State,Balance
+                        Exchange,2022-02-01 10:00:00,2022-02-01 10:00:00,Exchanged to SEK,-99,-1,DOGE,-99,DOGE,,,Completed,0
+                        Exchange,2022-02-01 10:00:00,2022-02-01 10:00:00,Exchanged from DOGE,297,0,SEK,297,SEK,,,Completed,1097
+                        Exchange,2021-12-31 10:00:00,2021-12-31 10:00:00,Exchanged to DOGE,-200,0,SEK,-200,SEK,,,Completed,800
+                        Exchange,2021-12-31 10:00:00,2021-12-31 10:00:00,Exchanged from SEK,100,0,DOGE,100,DOGE,,,Completed,100")?;
+        let path = PathBuf::from(file.path());
+        let doge = "DOGE".to_string();
+        let trades = block_on(async {
+            let rows = RevolutRow2022::read_exchanges_in_currency(&path, &doge).await?;
+            RevolutRow2022::rows_to_trades(&rows, &doge).await
+        })?;
+
+        /*
+         * When
+         */
+        let disposals = TaxableTrade::taxable_trades_all_currencies(&with_fee_disposals(trades), MissingCost::Abort)?;
+
+        /*
+         * Then
+         */
+        // 99 DOGE are sold for 297 SEK, 3 SEK each, and the fee of 1 DOGE is sold for 3 SEK
+        // that are deducted from the income of the sale
+        let rows: Vec<_> = disposals.iter().map(|t| (t.amount, t.income.amount(), t.net_income)).collect();
+        assert_eq!(rows, vec![
+            (dec!(-99), dec!(294), Some(dec!(96))),
+            (dec!(-1), dec!(3), Some(dec!(1))),
+        ]);
+
+        Ok(())
+    }
+}
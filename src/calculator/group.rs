@@ -0,0 +1,143 @@
+use anyhow::{anyhow, Result};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+use super::{lot, TaxableTrade};
+
+/// A column to group taxable trades by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupBy {
+    Currency,
+    Month,
+    Quarter,
+    Year,
+    /// Current or Savings
+    Product,
+}
+
+impl GroupBy {
+    fn header(&self) -> &'static str {
+        match self {
+            GroupBy::Currency => "Currency",
+            GroupBy::Month => "Month",
+            GroupBy::Quarter => "Quarter",
+            GroupBy::Year => "Year",
+            GroupBy::Product => "Product",
+        }
+    }
+
+    fn key(&self, t: &TaxableTrade) -> String {
+        let date = t.date.as_deref().unwrap_or_default();
+        match self {
+            GroupBy::Currency => t.currency.clone(),
+            GroupBy::Month => date.chars().take(7).collect(),
+            GroupBy::Quarter => {
+                let month: u32 = date.get(5..7).and_then(|m| m.parse().ok()).unwrap_or_default();
+                format!("{}-Q{}", t.year(), month.div_ceil(3))
+            }
+            GroupBy::Year => t.year(),
+            GroupBy::Product => lot::pool(t.is_vault).to_string(),
+        }
+    }
+}
+
+/// The income, cost and net income of the taxable trades with the same keys.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Group {
+    pub(crate) keys: Vec<String>,
+    pub(crate) trades: usize,
+    pub(crate) income: Decimal,
+    pub(crate) cost: Decimal,
+    pub(crate) net_income: Decimal,
+}
+
+impl Group {
+    /// Groups the taxable trades by the keys in `group_by`, sorted by the keys.
+    pub(crate) fn from_taxable_trades(taxable_trades: &[TaxableTrade], group_by: &[GroupBy]) -> Result<Vec<Group>> {
+        let mut groups: BTreeMap<Vec<String>, Group> = BTreeMap::new();
+
+        for t in taxable_trades {
+            let (cost, net_income) =
+                t.sum_cash_amount()
+                    .zip(t.net_income)
+                    .filter(|_| t.income.is_cash())
+                    .ok_or(anyhow!("Could not group {:?} {}: all values must be in the base currency. Try --estimate-prices", t.date, t.currency))?;
+
+            let keys: Vec<String> = group_by.iter().map(|g| g.key(t)).collect();
+            let group = groups.entry(keys.clone()).or_insert_with(|| Group { keys, ..Default::default() });
+            group.trades += 1;
+            group.income += t.income.amount();
+            group.cost += cost;
+            group.net_income += net_income;
+        }
+
+        Ok(groups.into_values().collect())
+    }
+
+    pub(crate) fn header(group_by: &[GroupBy]) -> Vec<String> {
+        group_by.iter()
+            .map(|g| g.header())
+            .chain(["Trades", "Income", "Cost", "Net Income"])
+            .map(String::from)
+            .collect()
+    }
+
+    pub(crate) fn to_record(&self) -> Vec<String> {
+        let mut record = self.keys.clone();
+        record.extend([
+            self.trades.to_string(),
+            self.income.to_string(),
+            self.cost.to_string(),
+            self.net_income.to_string(),
+        ]);
+        record
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::calculator::group::{Group, GroupBy};
+    use crate::calculator::{Money, TaxableTrade};
+    use rust_decimal_macros::dec;
+    use std::error::Error;
+
+    #[test]
+    fn should_group_by_quarter_and_product() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let trade = |date: &str, is_vault, net_income| TaxableTrade::new(
+            Some(date.to_string()),
+            "EOS".to_string(),
+            dec!(-10),
+            Money::new_cash("SEK".to_string(), dec!(100)),
+            vec![Money::new_cash("SEK".to_string(), net_income - dec!(100))],
+            Some(net_income)
+        ).with_vault(is_vault);
+        let taxable_trades = vec![
+            trade("2023-01-31 10:00:00", false, dec!(10)),
+            trade("2023-03-01 10:00:00", false, dec!(-5)),
+            trade("2023-03-02 10:00:00", true, dec!(20)),
+            trade("2023-04-01 10:00:00", false, dec!(1)),
+        ];
+
+        /*
+         * When
+         */
+        let group_by = [GroupBy::Quarter, GroupBy::Product];
+        let groups = Group::from_taxable_trades(&taxable_trades, &group_by)?;
+
+        /*
+         * Then
+         */
+        assert_eq!(Group::header(&group_by), vec!["Quarter", "Product", "Trades", "Income", "Cost", "Net Income"]);
+        let records: Vec<Vec<String>> = groups.iter().map(Group::to_record).collect();
+        assert_eq!(records, vec![
+            vec!["2023-Q1", "Current", "2", "200", "-195", "5"],
+            vec!["2023-Q1", "Savings", "1", "100", "-80", "20"],
+            vec!["2023-Q2", "Current", "1", "100", "-99", "1"],
+        ]);
+
+        Ok(())
+    }
+}
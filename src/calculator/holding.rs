@@ -0,0 +1,60 @@
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use super::{Currency, PriceBook};
+
+/// The quantity and cost basis left in one pool of a `CostBook`, valued at a market price
+/// when one is known.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct Holding {
+    #[serde(rename = "Currency")]
+    pub(crate) currency: Currency,
+
+    #[serde(rename = "Pool")]
+    pub(crate) pool: String,
+
+    #[serde(rename = "Quantity")]
+    pub(crate) quantity: Decimal,
+
+    // Empty when some of the cost is valued in another coin
+    #[serde(rename = "Average Cost")]
+    pub(crate) average_cost: Option<Decimal>,
+
+    #[serde(rename = "Cost Basis")]
+    pub(crate) basis: Option<Decimal>,
+
+    #[serde(rename = "Price")]
+    pub(crate) price: Option<Decimal>,
+
+    #[serde(rename = "Market Value")]
+    pub(crate) market_value: Option<Decimal>,
+
+    #[serde(rename = "Unrealized")]
+    pub(crate) unrealized: Option<Decimal>,
+}
+
+impl Holding {
+    pub(crate) fn new(currency: Currency, pool: &str, quantity: Decimal, basis: Option<Decimal>) -> Self {
+        Holding {
+            currency,
+            pool: pool.to_string(),
+            quantity,
+            average_cost: basis.map(|b| (b / quantity).round_dp(2)),
+            basis,
+            price: None,
+            market_value: None,
+            unrealized: None,
+        }
+    }
+
+    /// Values the holding at the latest price of its currency.
+    pub(crate) fn valued_at(mut self, prices: &PriceBook) -> Self {
+        if let Some(price) = prices.latest(&self.currency) {
+            let market_value = (self.quantity * price.price).round_dp(2);
+            self.price = Some(price.price);
+            self.market_value = Some(market_value);
+            self.unrealized = self.basis.map(|b| market_value - b);
+        }
+        self
+    }
+}
@@ -0,0 +1,53 @@
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use super::Money;
+
+/// The part of a cost in the `CostBook` that was deducted by a sale.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Lot {
+    // Dates of the acquisitions that make up the cost, empty for "schablonmetoden"
+    pub(crate) acquired: Vec<String>,
+    pub(crate) is_vault: bool,
+    pub(crate) quantity: Decimal,
+    pub(crate) cost: Money,
+}
+
+impl Lot {
+    pub(crate) fn pool(&self) -> &'static str {
+        pool(self.is_vault)
+    }
+}
+
+/// The name of the Revolut product that holds the pool.
+pub(crate) fn pool(is_vault: bool) -> &'static str {
+    match is_vault {
+        true => "Savings",
+        false => "Current",
+    }
+}
+
+/// A `Lot` printed together with the sale that deducted it.
+#[derive(Debug, Serialize)]
+pub(crate) struct ExplainedLot {
+    #[serde(rename = "Date")]
+    pub(crate) date: Option<String>,
+
+    #[serde(rename = "Currency")]
+    pub(crate) currency: String,
+
+    #[serde(rename = "Amount")]
+    pub(crate) amount: Decimal,
+
+    #[serde(rename = "Acquired")]
+    pub(crate) acquired: String,
+
+    #[serde(rename = "Pool")]
+    pub(crate) pool: String,
+
+    #[serde(rename = "Quantity")]
+    pub(crate) quantity: Decimal,
+
+    #[serde(rename = "Cost")]
+    pub(crate) cost: String,
+}
@@ -1,11 +1,33 @@
+mod card_spending;
 mod cost_book;
+pub(crate) mod fee;
+pub(crate) mod group;
+pub(crate) mod holding;
+pub(crate) mod lot;
 pub(crate) mod money;
+pub(crate) mod price_book;
+pub(crate) mod spread;
 pub(crate) mod taxable_trade;
+pub(crate) mod timeline;
 pub(crate) mod trade;
+pub(crate) mod what_if;
 
 pub(crate) type Currency = String;
 
-pub(crate) use self::cost_book::CostBook;
-pub(crate) use self::money::Money;
+pub(crate) use self::card_spending::CardSpending;
+pub use self::cost_book::MissingCost;
+pub(crate) use self::cost_book::{CostBook, CurrencyErrors, InsufficientCost};
+pub(crate) use self::fee::{with_fee_disposals, FeeReport};
+pub use self::group::GroupBy;
+pub(crate) use self::group::Group;
+pub(crate) use self::holding::Holding;
+pub(crate) use self::lot::{ExplainedLot, Lot};
+pub(crate) use self::money::{Estimate, Money};
+pub(crate) use self::price_book::{Price, PriceBook};
+pub(crate) use self::spread::{Spread, SpreadYear};
 pub(crate) use self::taxable_trade::TaxableTrade;
-pub(crate) use self::trade::{Direction, Trade};
\ No newline at end of file
+pub use self::timeline::Timeline;
+pub(crate) use self::timeline::Snapshot;
+pub(crate) use self::trade::{insert_by_date, Direction, Kind, Trade};
+pub use self::what_if::WhatIf;
+pub(crate) use self::what_if::WhatIfTotal;
\ No newline at end of file
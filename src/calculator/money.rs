@@ -1,7 +1,8 @@
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 
-use super::Currency;
+use super::{Currency, PriceBook};
+use super::price_book::{format_gap, Price};
 
 #[derive(Debug, PartialEq, Clone)]
 pub(crate) enum Money {
@@ -24,6 +25,13 @@ impl Money {
         match self { Money::Cash(_) => true, Money::Coupon(_) => false }
     }
 
+    pub(crate) fn currency(&self) -> &Currency {
+        match self {
+            Money::Cash(cash) => &cash.currency,
+            Money::Coupon(coupon) => &coupon.currency
+        }
+    }
+
     pub(crate) fn amount(&self) -> Decimal {
         match self {
             Money::Cash(cash) => cash.amount,
@@ -31,6 +39,24 @@ impl Money {
         }
     }
 
+    /// Returns the part `quantity / total` of the amount. Cash is rounded to two decimals (öre),
+    /// so that the rounding remainder stays with the rest of the amount.
+    pub(crate) fn allocate(&self, quantity: Decimal, total: Decimal) -> Decimal {
+        let amount = self.amount() * quantity / total;
+        match self {
+            Money::Cash(_) => amount.round_dp(2),
+            Money::Coupon(_) => amount,
+        }
+    }
+
+    /// Returns a copy of this `Money` with the amount multiplied by `factor`.
+    pub(crate) fn scaled(&self, factor: Decimal) -> Money {
+        match self {
+            Money::Cash(cash) => Money::new_cash(cash.currency.clone(), cash.amount * factor),
+            Money::Coupon(coupon) => Money::new_coupon(coupon.currency.clone(), coupon.amount * factor, coupon.date.clone())
+        }
+    }
+
     pub(crate) fn deduct(&mut self, amount: Decimal) -> Money {
         match self {
             Money::Cash(cash) => {
@@ -44,7 +70,21 @@ impl Money {
         }
     }
 
-    pub(crate) fn to_net_income(&self, costs: &Vec<Money>) -> Option<Decimal> {
+    /// Values a `Money::Coupon` in the `base` currency, using the price observed closest in time
+    /// to the date of the coupon. Returns the valued cash and the `Estimate` behind it.
+    pub(crate) fn estimate(&self, prices: &PriceBook, base: &Currency) -> Option<(Money, Estimate)> {
+        match self {
+            Money::Cash(_) => None,
+            Money::Coupon(coupon) => {
+                let (price, gap) = prices.nearest(&coupon.currency, &coupon.date)?;
+                let cash = Money::new_cash(base.clone(), coupon.amount * price.price);
+                let estimate = Estimate { coupon: coupon.clone(), price: price.clone(), gap };
+                Some((cash, estimate))
+            }
+        }
+    }
+
+    pub(crate) fn to_net_income(&self, costs: &[Money]) -> Option<Decimal> {
         let all_cash = costs.iter().all(|c| c.is_cash());
         match (self, all_cash) {
             (Money::Cash(cash), true) => {
@@ -76,4 +116,26 @@ pub(crate) struct Coupon {
     currency: Currency,
     amount: Decimal,
     date: String
-}
\ No newline at end of file
+}
+
+/// A coupon valued in the base currency with a price observed at another point in time.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Estimate {
+    coupon: Coupon,
+    price: Price,
+    gap: chrono::Duration,
+}
+
+impl std::fmt::Display for Estimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} at {} each, implied by {} ({} apart)",
+            self.coupon.amount,
+            self.coupon.currency,
+            self.price.price,
+            self.price.source,
+            format_gap(&self.gap)
+        )
+    }
+}
@@ -0,0 +1,117 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+use super::Currency;
+
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// The price of one unit of `currency` in the base currency, observed at `date`.
+/// `source` describes where the price comes from, e.g. the statement rows it was implied from.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Price {
+    pub(crate) currency: Currency,
+    pub(crate) date: NaiveDateTime,
+    pub(crate) price: Decimal,
+    pub(crate) source: String,
+}
+
+/// Prices observed over time, grouped by currency.
+#[derive(Debug, Default)]
+pub(crate) struct PriceBook {
+    prices: HashMap<Currency, Vec<Price>>,
+}
+
+impl PriceBook {
+    pub(crate) fn new(prices: Vec<Price>) -> PriceBook {
+        let mut book = PriceBook::default();
+        for price in prices {
+            book.prices.entry(price.currency.clone())
+                .or_default()
+                .push(price);
+        }
+        book.prices.values_mut()
+            .for_each(|prices| prices.sort_by_key(|price| price.date));
+        book
+    }
+
+    /// Finds the most recently observed price of `currency`.
+    pub(crate) fn latest(&self, currency: &Currency) -> Option<&Price> {
+        self.prices.get(currency)?.last()
+    }
+
+    /// Finds the price of `currency` observed closest in time to `date`.
+    /// Returns the price together with the time between the observation and `date`.
+    pub(crate) fn nearest(&self, currency: &Currency, date: &str) -> Option<(&Price, Duration)> {
+        let date = parse_date(date)?;
+        self.prices.get(currency)?
+            .iter()
+            .map(|price| {
+                let gap = price.date - date;
+                (price, if gap < Duration::zero() { -gap } else { gap })
+            })
+            .min_by_key(|(_, gap)| *gap)
+    }
+}
+
+/// Parses a date with time, or a date alone as midnight.
+pub(crate) fn parse_date(date: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(date, DATE_FORMAT).ok()
+        .or_else(|| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0))
+}
+
+/// Formats a duration as e.g. `1d 2h 3m`.
+pub(crate) fn format_gap(gap: &Duration) -> String {
+    let parts: Vec<String> =
+        [(gap.num_days(), "d"), (gap.num_hours() % 24, "h"), (gap.num_minutes() % 60, "m"), (gap.num_seconds() % 60, "s")]
+            .iter()
+            .filter(|(n, _)| *n != 0)
+            .map(|(n, unit)| format!("{}{}", n, unit))
+            .collect();
+
+    match parts.is_empty() {
+        true => "0s".to_string(),
+        false => parts.join(" "),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::calculator::price_book::{format_gap, parse_date, Price, PriceBook};
+    use chrono::Duration;
+    use rust_decimal_macros::dec;
+    use std::error::Error;
+
+    #[test]
+    fn should_find_nearest_price() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let price = |date: &str, price| Price {
+            currency: "EOS".to_string(),
+            date: parse_date(date).unwrap(),
+            price,
+            source: "".to_string(),
+        };
+        let book = PriceBook::new(vec![
+            price("2022-03-05 10:00:00", dec!(22)),
+            price("2022-03-01 10:00:00", dec!(20)),
+            price("2022-03-02 09:00:00", dec!(21)),
+        ]);
+
+        /*
+         * When
+         */
+        let (found, gap) = book.nearest(&"EOS".to_string(), "2022-03-02 12:30:00").unwrap();
+
+        /*
+         * Then
+         */
+        assert_eq!(found.price, dec!(21));
+        assert_eq!(gap, Duration::minutes(210));
+        assert_eq!(format_gap(&gap), "3h 30m");
+        assert!(book.nearest(&"BTC".to_string(), "2022-03-02 12:30:00").is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,353 @@
+use anyhow::{anyhow, Result};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use super::price_book::format_gap;
+use super::{Currency, Direction, Kind, PriceBook, TaxableTrade, Trade};
+
+/// The difference between the price of an exchange and the reference price at the same time,
+/// i.e. the cost of the spread that is not reported as a fee.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct Spread {
+    #[serde(rename = "Date")]
+    pub(crate) date: String,
+
+    #[serde(rename = "Type")]
+    pub(crate) direction: Direction,
+
+    #[serde(rename = "Currency")]
+    pub(crate) currency: Currency,
+
+    #[serde(rename = "Amount")]
+    pub(crate) amount: Decimal,
+
+    // Fiat amount without fees / Amount
+    #[serde(rename = "Price")]
+    pub(crate) price: Decimal,
+
+    #[serde(rename = "Reference Price")]
+    pub(crate) reference_price: Decimal,
+
+    #[serde(rename = "Reference Gap")]
+    pub(crate) reference_gap: String,
+
+    #[serde(rename = "Spread Cost")]
+    pub(crate) cost: Decimal,
+
+    #[serde(rename = "Spread %")]
+    pub(crate) percent: Decimal,
+
+    // Fiat amount without fees
+    #[serde(skip)]
+    pub(crate) volume: Decimal,
+}
+
+impl Spread {
+    /// Compares the price of every exchange against `base_currency` in `trades` with the reference
+    /// price observed closest in time. Card payments and exchanges against other currencies are
+    /// left out. Fails with the exchanges that have no reference price.
+    pub(crate) fn spreads(trades: &[Trade], prices: &PriceBook, base_currency: &Currency) -> Result<Vec<Self>> {
+        let mut spreads = vec![];
+        let mut unpriced = vec![];
+
+        for trade in trades.iter().filter(|t| Self::is_spread_exchange(t, base_currency)) {
+            match Self::try_new(trade, prices) {
+                Some(spread) => spreads.push(spread),
+                None => unpriced.push(format!("{} {}", trade.date, trade.paid_currency)),
+            }
+        }
+
+        match unpriced.is_empty() {
+            true => Ok(spreads),
+            false => Err(anyhow!("No reference price for {} exchanges, add them to --price-file: {}", unpriced.len(), unpriced.join(", "))),
+        }
+    }
+
+    fn is_spread_exchange(trade: &Trade, base_currency: &Currency) -> bool {
+        trade.kind == Kind::Exchange
+            && trade.exchanged_currency.eq(base_currency)
+            && !(trade.paid_amount + trade.paid_fee).is_zero()
+    }
+
+    /// Compares the price of an exchange with the reference price observed closest in time.
+    /// Returns `None` when the currency has no reference prices.
+    fn try_new(trade: &Trade, prices: &PriceBook) -> Option<Self> {
+        // The amounts before fees, which are reported separately
+        let amount = (trade.paid_amount + trade.paid_fee).abs();
+        let fiat_amount = match trade.direction {
+            Direction::Buy => trade.exchanged_amount.abs() - trade.exchanged_fee,
+            Direction::Sell => trade.exchanged_amount.abs() + trade.exchanged_fee,
+        };
+
+        let (reference, gap) = prices.nearest(&trade.paid_currency, &trade.date)?;
+        let price = fiat_amount / amount;
+        let reference_value = reference.price * amount;
+        let cost = match trade.direction {
+            Direction::Buy => fiat_amount - reference_value,
+            Direction::Sell => reference_value - fiat_amount,
+        };
+
+        Some(Spread {
+            date: trade.date.clone(),
+            direction: trade.direction.clone(),
+            currency: trade.paid_currency.clone(),
+            amount,
+            price: price.round_dp(8),
+            reference_price: reference.price,
+            reference_gap: format_gap(&gap),
+            cost: cost.round_dp(2),
+            percent: match reference_value.is_zero() {
+                true => dec!(0),
+                false => (cost / reference_value * dec!(100)).round_dp(2),
+            },
+            volume: fiat_amount,
+        })
+    }
+}
+
+/// The spread costs of a year, next to the net income of the taxable trades of the year.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct SpreadYear {
+    #[serde(rename = "Year")]
+    pub(crate) year: String,
+
+    #[serde(rename = "Exchanges")]
+    pub(crate) exchanges: usize,
+
+    // Fiat amount of the exchanges without fees
+    #[serde(rename = "Volume")]
+    pub(crate) volume: Decimal,
+
+    #[serde(rename = "Spread Cost")]
+    pub(crate) cost: Decimal,
+
+    #[serde(rename = "Spread %")]
+    pub(crate) percent: Decimal,
+
+    #[serde(rename = "Net Income")]
+    pub(crate) net_income: Decimal,
+}
+
+impl SpreadYear {
+    /// Sums the spreads and the net income of the taxable trades of every year. Errors if the
+    /// net income of a taxable trade is unknown because some of its costs are not cash.
+    pub(crate) fn years(spreads: &[Spread], taxable_trades: &[TaxableTrade]) -> Result<Vec<SpreadYear>> {
+        let mut years: BTreeMap<String, (usize, Decimal, Decimal, Decimal)> = BTreeMap::new();
+        for spread in spreads {
+            let (exchanges, volume, cost, _) = years.entry(spread.date.chars().take(4).collect()).or_default();
+            *exchanges += 1;
+            *volume += spread.volume;
+            *cost += spread.cost;
+        }
+        for t in taxable_trades {
+            years.entry(t.year()).or_default().3 +=
+                t.net_income.ok_or(anyhow!("All costs must be cash, but {:?} {} has costs in another coin", t.date, t.currency))?;
+        }
+
+        let years = years.into_iter()
+            .map(|(year, (exchanges, volume, cost, net_income))| SpreadYear {
+                year,
+                exchanges,
+                volume,
+                cost,
+                percent: match volume.is_zero() {
+                    true => dec!(0),
+                    false => (cost / volume * dec!(100)).round_dp(2),
+                },
+                net_income,
+            })
+            .collect();
+
+        Ok(years)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::calculator::spread::{Spread, SpreadYear};
+    use crate::calculator::price_book::parse_date;
+    use crate::calculator::{Direction, Kind, Price, PriceBook, Trade};
+    use rust_decimal_macros::dec;
+    use std::error::Error;
+
+    #[test]
+    fn should_calculate_spread() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let prices = PriceBook::new(vec![Price {
+            currency: "EOS".to_string(),
+            date: parse_date("2023-01-01").unwrap(),
+            price: dec!(19.5),
+            source: "".to_string(),
+        }]);
+        let trade = |direction, paid_amount, exchanged_amount, exchanged_fee| Trade {
+            direction,
+            paid_currency: "EOS".to_string(),
+            paid_amount,
+            exchanged_currency: "SEK".to_string(),
+            exchanged_amount,
+            date: "2023-01-01 10:00:00".to_string(),
+            is_vault: false,
+            paid_fee: dec!(0),
+            exchanged_fee,
+            kind: Kind::Exchange
+        };
+
+        /*
+         * When
+         */
+        let buy = Spread::try_new(&trade(Direction::Buy, dec!(30), dec!(-609.15), dec!(9.15)), &prices);
+        let sell = Spread::try_new(&trade(Direction::Sell, dec!(-30), dec!(574.86), dec!(5.14)), &prices);
+
+        /*
+         * Then
+         */
+        let buy = buy.unwrap();
+        assert_eq!(buy.price, dec!(20));
+        assert_eq!(buy.cost, dec!(15));
+        assert_eq!(buy.percent, dec!(2.56));
+        assert_eq!(buy.reference_gap, "10h");
+
+        let sell = sell.unwrap();
+        assert_eq!(sell.price, dec!(19.33333333));
+        assert_eq!(sell.cost, dec!(5));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_leave_out_card_payments_and_other_currencies() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let prices = PriceBook::new(vec![Price {
+            currency: "EOS".to_string(),
+            date: parse_date("2023-01-01").unwrap(),
+            price: dec!(19.5),
+            source: "".to_string(),
+        }]);
+        let trade = |exchanged_currency: &str, kind| Trade {
+            direction: Direction::Sell,
+            paid_currency: "EOS".to_string(),
+            paid_amount: dec!(-30),
+            exchanged_currency: exchanged_currency.to_string(),
+            exchanged_amount: dec!(574.86),
+            date: "2023-01-01 10:00:00".to_string(),
+            is_vault: false,
+            paid_fee: dec!(0),
+            exchanged_fee: dec!(5.14),
+            kind
+        };
+        let trades = vec![
+            trade("SEK", Kind::card_payment("Payment to Amazon")),
+            trade("USD", Kind::Exchange),
+            trade("SEK", Kind::Exchange),
+        ];
+
+        /*
+         * When
+         */
+        let spreads = Spread::spreads(&trades, &prices, &"SEK".to_string())?;
+
+        /*
+         * Then
+         */
+        assert_eq!(spreads.len(), 1);
+        assert_eq!(spreads[0].cost, dec!(5));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_on_exchanges_without_reference_price() {
+        /*
+         * Given
+         */
+        let prices = PriceBook::new(vec![Price {
+            currency: "EOS".to_string(),
+            date: parse_date("2023-01-01").unwrap(),
+            price: dec!(19.5),
+            source: "".to_string(),
+        }]);
+        let trade = |paid_currency: &str, date: &str| Trade {
+            direction: Direction::Buy,
+            paid_currency: paid_currency.to_string(),
+            paid_amount: dec!(1),
+            exchanged_currency: "SEK".to_string(),
+            exchanged_amount: dec!(-100),
+            date: date.to_string(),
+            is_vault: false,
+            paid_fee: dec!(0),
+            exchanged_fee: dec!(0),
+            kind: Kind::Exchange
+        };
+        let trades = vec![
+            trade("BTC", "2023-01-01 10:00:00"),
+            trade("EOS", "2023-01-02 10:00:00"),
+            trade("ETH", "2023-01-03 10:00:00"),
+        ];
+
+        /*
+         * When
+         */
+        let err = Spread::spreads(&trades, &prices, &"SEK".to_string()).unwrap_err();
+
+        /*
+         * Then
+         */
+        assert_eq!(err.to_string(), "No reference price for 2 exchanges, add them to --price-file: 2023-01-01 10:00:00 BTC, 2023-01-03 10:00:00 ETH");
+    }
+
+    #[test]
+    fn should_sum_spreads_by_year() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let prices = PriceBook::new(vec![Price {
+            currency: "EOS".to_string(),
+            date: parse_date("2023-01-01").unwrap(),
+            price: dec!(19.5),
+            source: "".to_string(),
+        }]);
+        let trade = |direction, paid_amount, exchanged_amount, exchanged_fee| Trade {
+            direction,
+            paid_currency: "EOS".to_string(),
+            paid_amount,
+            exchanged_currency: "SEK".to_string(),
+            exchanged_amount,
+            date: "2023-01-01 10:00:00".to_string(),
+            is_vault: false,
+            paid_fee: dec!(0),
+            exchanged_fee,
+            kind: Kind::Exchange
+        };
+        let spreads = Spread::spreads(&[
+            trade(Direction::Buy, dec!(30), dec!(-609.15), dec!(9.15)),
+            trade(Direction::Sell, dec!(-30), dec!(574.86), dec!(5.14)),
+        ], &prices, &"SEK".to_string())?;
+
+        /*
+         * When
+         */
+        let years = SpreadYear::years(&spreads, &[])?;
+
+        /*
+         * Then
+         */
+        assert_eq!(years, vec![
+            SpreadYear {
+                year: "2023".to_string(),
+                exchanges: 2,
+                volume: dec!(1180),
+                cost: dec!(20),
+                percent: dec!(1.69),
+                net_income: dec!(0),
+            }
+        ]);
+
+        Ok(())
+    }
+}
@@ -1,27 +1,31 @@
 use anyhow::{anyhow, Result};
-use log::debug;
+use log::warn;
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 use serde::ser::SerializeStruct;
 use serde::{Serialize, Serializer};
-use std::collections::{HashMap, HashSet};
+use std::collections::BTreeMap;
 
-use super::{CostBook, Currency, Direction, Trade, Money};
-use crate::{Config, writer};
-use crate::skatteverket::SruFile;
+use super::{CostBook, Currency, Estimate, ExplainedLot, Group, Kind, Lot, MissingCost, Trade, Money, PriceBook};
+use crate::{Config, SruFileConfig, writer};
+use crate::skatteverket::{system_info, InfoSru, Ink1, Ink1Box, Ink1Spec, K4Spec, Rounding, SruDiff, SruFile, TaxEstimate, Taxpayer};
 
 // 1. Bought Crypto 1 from SEK      (cost in SEK),  sold to SEK      (sales in SEK)
 // 2. Bought Crypto 1 from SEK      (cost in SEK),  sold to Crypto 2 (SEK price as sales)
 // 3. Bought from Crypto 2 (SEK price as cost),     sold to Crypto 3 (SEK price as sales)
 // 4. Bought from Crypto 3 (SEK price as cost),     sold to SEK      (sales in SEK)
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) struct TaxableTrade {
-    date: Option<String>,
+    pub(crate) date: Option<String>,
     pub(crate) currency: Currency,             // Valutakod
     pub(crate) amount: Decimal,                // Antal
     pub(crate) income: Money,                  // Försäljningspris
     pub(crate) costs: Vec<Money>,              // Omkostnadsbelopp
     pub(crate) net_income: Option<Decimal>,    // Vinst/förlust
+    pub(crate) estimates: Vec<Estimate>,
+    pub(crate) lots: Vec<Lot>,
+    pub(crate) kind: Kind,
+    pub(crate) is_vault: bool,
 }
 
 impl Serialize for TaxableTrade {
@@ -40,6 +44,37 @@ impl Serialize for TaxableTrade {
     }
 }
 
+impl std::fmt::Display for TaxableTrade {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.date {
+            Some(date) => write!(f, "{} {} {}", date, self.amount, self.currency),
+            None => write!(f, "{} {}", self.amount, self.currency),
+        }
+    }
+}
+
+/// Serializes a `TaxableTrade` with an extra column listing the estimates behind its values.
+struct EstimatedTaxableTrade<'a>(&'a TaxableTrade);
+
+impl Serialize for EstimatedTaxableTrade<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer,
+    {
+        let trade = self.0;
+        let estimates: Vec<String> = trade.estimates.iter().map(|e| e.to_string()).collect();
+
+        let mut state = serializer.serialize_struct("EstimatedTaxableTrade", 7)?;
+        state.serialize_field("Date", &trade.date)?;
+        state.serialize_field("Currency", &trade.currency)?;
+        state.serialize_field("Amount", &trade.amount)?;
+        state.serialize_field("Income", &format!("{}", trade.income))?;
+        state.serialize_field("Cost", &trade.costs_to_string())?;
+        state.serialize_field("Net Income", &trade.net_income)?;
+        state.serialize_field("Estimate", &estimates.join("; "))?;
+        state.end()
+    }
+}
+
 impl TaxableTrade {
     pub(crate) fn new(
         date: Option<String>,
@@ -56,9 +91,88 @@ impl TaxableTrade {
             income,
             costs,
             net_income,
+            estimates: vec![],
+            lots: vec![],
+            kind: Kind::Exchange,
+            is_vault: false,
         }
     }
 
+    /// The year of the trade, or an empty string for a sum of trades.
+    pub(crate) fn year(&self) -> String {
+        self.date.as_ref().map(|d| d.chars().take(4).collect()).unwrap_or_default()
+    }
+
+    /// The columns of `to_record`, the same as when the trade is serialized.
+    pub(crate) fn header() -> Vec<String> {
+        ["Date", "Currency", "Amount", "Income", "Cost", "Net Income"].map(String::from).to_vec()
+    }
+
+    /// The trade as the record of a csv or a table, the same as when it is serialized.
+    pub(crate) fn to_record(&self) -> Vec<String> {
+        vec![
+            self.date.clone().unwrap_or_default(),
+            self.currency.clone(),
+            self.amount.to_string(),
+            self.income.to_string(),
+            self.costs_to_string(),
+            self.net_income.map(|n| n.to_string()).unwrap_or_default(),
+        ]
+    }
+
+    pub(crate) fn with_lots(mut self, lots: Vec<Lot>) -> Self {
+        self.lots = lots;
+        self
+    }
+
+    pub(crate) fn with_kind(mut self, kind: Kind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub(crate) fn with_vault(mut self, is_vault: bool) -> Self {
+        self.is_vault = is_vault;
+        self
+    }
+
+    /// Lists the lots deducted by this trade, one row per lot.
+    pub(crate) fn explain(&self) -> Vec<ExplainedLot> {
+        self.lots.iter()
+            .map(|lot|
+                ExplainedLot {
+                    date: self.date.clone(),
+                    currency: self.currency.clone(),
+                    amount: self.amount,
+                    acquired: match lot.acquired.is_empty() {
+                        true => "Schablon".to_string(),
+                        false => lot.acquired.join(", "),
+                    },
+                    pool: lot.pool().to_string(),
+                    quantity: lot.quantity,
+                    cost: lot.cost.to_string(),
+                }
+            )
+            .collect()
+    }
+
+    /// Values the coupons in the income and the costs in `base_currency`, using the prices
+    /// observed closest in time to each coupon. Coupons without any observed price are kept.
+    pub(crate) fn estimate_coupons(&mut self, prices: &PriceBook, base_currency: &Currency) {
+        if let Some((cash, estimate)) = self.income.estimate(prices, base_currency) {
+            self.income = cash;
+            self.estimates.push(estimate);
+        }
+
+        for cost in self.costs.iter_mut() {
+            if let Some((cash, estimate)) = cost.estimate(prices, base_currency) {
+                *cost = cash;
+                self.estimates.push(estimate);
+            }
+        }
+
+        self.net_income = self.income.to_net_income(&self.costs);
+    }
+
     fn costs_to_string(&self) -> String {
         if let Some(sum) = self.sum_cash_amount() {
             sum.to_string()
@@ -68,6 +182,30 @@ impl TaxableTrade {
         }
     }
 
+    /// Returns the part `share` of the trade, e.g. the part of one owner of a joint account.
+    pub(crate) fn scaled(&self, share: Decimal) -> TaxableTrade {
+        TaxableTrade {
+            amount: self.amount * share,
+            income: self.income.scaled(share),
+            costs: self.costs.iter().map(|c| c.scaled(share)).collect(),
+            net_income: self.net_income.map(|n| n * share),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the trade in whole kronor, with the net income as the difference of the rounded
+    /// income and cost, as on the K4 form.
+    pub(crate) fn try_rounded(&self) -> Result<TaxableTrade> {
+        let rounding = Rounding::try_new(self).ok_or(anyhow!("All costs must be cash"))?;
+        let currency = self.income.currency().clone();
+        Ok(TaxableTrade {
+            income: Money::new_cash(currency.clone(), rounding.rounded_income),
+            costs: vec![Money::new_cash(currency, rounding.rounded_costs)],
+            net_income: Some(rounding.rounded_net_income),
+            ..self.clone()
+        })
+    }
+
     pub(crate) fn sum_cash_amount(&self) -> Option<Decimal> {
         if self.costs.iter().all(|c| c.is_cash()) {
             let sum =
@@ -79,89 +217,62 @@ impl TaxableTrade {
         }
     }
 
-    pub(crate) async fn taxable_trades_all_currencies(trades: &Vec<Trade>) -> Vec<TaxableTrade> {
-        let mut unique_pairs: HashSet<(Currency, Currency)> = HashSet::new();
-
-        for t in trades {
-            let pair = (t.paid_currency.clone(), t.exchanged_currency.clone());
-            unique_pairs.insert(pair);
-        }
-
-        let mut taxable_trades: Vec<TaxableTrade> = vec![];
+    /// Calculates the taxable trades of every currency in `trades`, sorted by date.
+    pub(crate) fn taxable_trades_all_currencies(
+        trades: &[Trade],
+        missing_cost: MissingCost
+    ) -> Result<Vec<TaxableTrade>> {
+        let mut taxable_trades: Vec<TaxableTrade> =
+            CostBook::replay_all(trades, missing_cost)?
+                .into_iter()
+                .flat_map(|(_, taxable_trades)| taxable_trades)
+                .collect();
 
-        for (paid_currency, exchanged_currency) in unique_pairs {
-            let result =
-                Self::taxable_trades(
-                    &trades,
-                    &paid_currency,
-                    &exchanged_currency
-                ).await.unwrap();
-            taxable_trades.extend(result);
-        }
+        taxable_trades.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.currency.cmp(&b.currency)));
 
-        taxable_trades
+        Ok(taxable_trades)
     }
 
+    /// Calculates the taxable trades of `currency` exchanged to `base_currency`. With
+    /// `with_swaps`, trades exchanged to other currencies are kept in the book as coupons, to be
+    /// valued by `estimate_coupons`.
     pub(crate) async fn taxable_trades(
-        trades: &Vec<Trade>,
+        trades: &[Trade],
         currency: &Currency,
-        base_currency: &Currency
+        base_currency: &Currency,
+        with_swaps: bool,
+        missing_cost: MissingCost
     ) -> Result<Vec<TaxableTrade>> {
-        let book = CostBook::new(currency.clone(), base_currency.clone());
-
-        let mut err = Ok(());
-
-        let (taxable_trades, book) =
+        let trades: Vec<&Trade> =
             trades.iter()
-                .fold((vec![], book), |(mut acc, mut book), trade| {
-                    let currency_match =
-                        trade.paid_currency.eq(&book.currency)
-                            && trade.exchanged_currency.eq(&book.base_currency);
-
-                    if currency_match {
-                        match trade.direction {
-                            Direction::Buy =>
-                                book.add_buy(trade),
-                            Direction::Sell => {
-                                match book.add_sell(trade) {
-                                    Ok(taxable_trade) => acc.push(taxable_trade),
-                                    Err(e) => err = Err(e),
-                                }
-                            }
-                        }
-                    }
-
-                    (acc, book)
-                });
-
-        err?;
+                .filter(|t| t.paid_currency.eq(currency))
+                .filter(|t| with_swaps || t.exchanged_currency.eq(base_currency))
+                .collect();
 
-        debug!("Remaining costs for {:?}:", book.currency);
-        book.costs.iter().for_each(|c| debug!("{:?}", c));
-        debug!("Taxable transactions:");
-        taxable_trades.iter().for_each(|t| debug!("{:?}", t));
+        let mut book =
+            CostBook::new(currency.clone(), base_currency.clone())
+                .with_missing_cost(missing_cost);
 
-        Ok(taxable_trades)
+        book.replay(&trades)
     }
 
-    pub(crate) async fn print_taxable_trades(
-        taxable_trades: Vec<TaxableTrade>,
-        config: &Config
-    ) -> Result<()> {
-        let taxable_trades =
-            taxable_trades.into_iter()
-                .filter(|t| {
-                    config.year_traded
-                        .map(|year_traded|
-                            t.date.as_ref().map(|date|
-                                date.contains(&year_traded.to_string())
-                            )
+    /// Keeps the taxable trades of `--year-traded`.
+    fn in_year_traded(taxable_trades: Vec<TaxableTrade>, config: &Config) -> Vec<TaxableTrade> {
+        taxable_trades.into_iter()
+            .filter(|t| {
+                config.year_traded
+                    .and_then(|year_traded|
+                        t.date.as_ref().map(|date|
+                            date.contains(&year_traded.to_string())
                         )
-                        .flatten()
-                        .unwrap_or(true)
-                })
-                .collect();
+                    )
+                    .unwrap_or(true)
+            })
+            .collect()
+    }
 
+    /// Sums the taxable trades by currency with `--sum`, and rounds them with `--round`.
+    fn summed_and_rounded(taxable_trades: Vec<TaxableTrade>, config: &Config) -> Result<Vec<TaxableTrade>> {
         let taxable_trades =
             if config.sum {
                 TaxableTrade::try_sum_cash_amount_by_currency(&taxable_trades)?
@@ -169,12 +280,79 @@ impl TaxableTrade {
                 taxable_trades
             };
 
-        if let Some(sru_conf) = &config.sru_file_config {
-            Self::print_sru_file(
-                &taxable_trades,
-                sru_conf.sru_org_num.clone(),
-                sru_conf.sru_org_name.clone()
-            ).await?;
+        let taxable_trades =
+            if config.round {
+                taxable_trades.iter().map(TaxableTrade::try_rounded).collect::<Result<Vec<_>>>()?
+            } else {
+                taxable_trades
+            };
+
+        Ok(taxable_trades)
+    }
+
+    pub(crate) async fn print_taxable_trades(
+        taxable_trades: Vec<TaxableTrade>,
+        config: &Config
+    ) -> Result<()> {
+        let taxable_trades = Self::in_year_traded(taxable_trades, config);
+
+        if config.explain {
+            let lots: Vec<ExplainedLot> = taxable_trades.iter().flat_map(TaxableTrade::explain).collect();
+            writer::print_csv_rows(&lots).await?;
+            return Ok(());
+        }
+
+        if !config.group_by.is_empty() {
+            let groups = Group::from_taxable_trades(&taxable_trades, &config.group_by)?;
+            let header = Group::header(&config.group_by);
+            let records: Vec<Vec<String>> = groups.iter().map(Group::to_record).collect();
+            match config.table {
+                true => writer::print_table(&header, &records)?,
+                false => writer::print_records(&header, &records).await?,
+            }
+            return Ok(());
+        }
+
+        // The K4 forms take the share of every --taxpayer before the trades are summed and rounded
+        let unsummed = taxable_trades.clone();
+        let taxable_trades = Self::summed_and_rounded(taxable_trades, config)?;
+
+        if config.tax_estimate {
+            let estimate = TaxEstimate::try_new(&taxable_trades, config.other_capital_income)?;
+            writer::print_csv_rows(&[estimate]).await?;
+        } else if let Some(path) = &config.diff_sru {
+            Self::print_sru_diff(&taxable_trades, path, config).await?;
+        } else if config.print_rounding {
+            let mut rows = taxable_trades.iter()
+                .map(Rounding::try_new)
+                .collect::<Option<Vec<_>>>()
+                .ok_or(anyhow!("All costs must be cash"))?;
+            rows.push(Rounding::total(&rows));
+            writer::print_csv_rows(&rows).await?;
+        } else if config.print_sru_totals {
+            let spec = K4Spec::for_year(config.income_year)?;
+            let sru_file = Self::try_sru_file(&unsummed, &spec, config).await?;
+            writer::print_csv_rows(&sru_file.totals(&spec)?).await?;
+        } else if config.ink1 {
+            let spec = K4Spec::for_year(config.income_year)?;
+            let sru_file = Self::try_sru_file(&unsummed, &spec, config).await?;
+            let boxes: Vec<Ink1Box> = Ink1::of(&sru_file, &spec)?.iter().flat_map(Ink1::boxes).collect();
+            writer::print_csv_rows(&boxes).await?;
+        } else if let Some(sru_conf) = &config.sru_file_config {
+            taxable_trades.iter()
+                .flat_map(|t| t.estimates.iter().map(move |e| (t, e)))
+                .for_each(|(t, e)| warn!("{:?} {} is valued with an estimate: {}", t.date, t.currency, e));
+            if !config.round {
+                warn!("The SRU file has whole kronor, but the CSV output of the same statement has öre. Use --round to print the same figures in the CSV output");
+            }
+
+            Self::print_sru_file(&unsummed, sru_conf, config).await?;
+        } else if config.estimate_prices {
+            let rows: Vec<EstimatedTaxableTrade> = taxable_trades.iter().map(EstimatedTaxableTrade).collect();
+            writer::print_csv_rows(&rows).await?;
+        } else if config.table {
+            let records: Vec<Vec<String>> = taxable_trades.iter().map(TaxableTrade::to_record).collect();
+            writer::print_table(&TaxableTrade::header(), &records)?;
         } else {
             writer::print_csv_rows(&taxable_trades).await?;
         }
@@ -182,36 +360,141 @@ impl TaxableTrade {
         Ok(())
     }
 
-    async fn print_sru_file(
-        taxable_trades: &Vec<TaxableTrade>,
-        org_num: String,
-        name: Option<String>
-    ) -> Result<()> {
-        let mut res = Ok(());
-        SruFile::try_new(taxable_trades, org_num, name)
-            .map(|sru_file| {
+    async fn print_sru_file(taxable_trades: &[TaxableTrade], sru_conf: &SruFileConfig, config: &Config) -> Result<()> {
+        let spec = K4Spec::for_year(config.income_year)?;
+
+        let sru_file = Self::try_sru_file(taxable_trades, &spec, config).await?;
+        let sru_file = match sru_conf.sru_ink1 {
+            true => sru_file.with_ink1(&spec, &Ink1Spec::for_year(config.income_year)?)?,
+            false => sru_file,
+        };
+        let inputs: Vec<&std::path::PathBuf> =
+            std::iter::once(&config.path)
+                .chain(&config.price_file)
+                .chain(sru_conf.taxpayers.iter().filter_map(|t| t.path.as_ref()))
+                .collect();
+        let sru_file = sru_file.with_system_info(system_info(&inputs, &config.sru_options())?);
+        let sru_file = match sru_conf.sru_timestamp {
+            Some(created) => sru_file.created_at(created),
+            None => sru_file,
+        };
+
+        sru_file.validate(&spec)?;
+        match &sru_conf.sru_dir {
+            Some(dir) => {
+                let info_sru = InfoSru::try_new(sru_conf, SruFile::FILE_NAME)?;
+                std::fs::create_dir_all(dir)?;
+                sru_file.write(std::fs::File::create(dir.join(SruFile::FILE_NAME))?, sru_conf.sru_crlf)?;
+                info_sru.write(std::fs::File::create(dir.join(InfoSru::FILE_NAME))?, sru_conf.sru_crlf)?;
+            }
+            None => {
                 let stdout = std::io::stdout();
                 let handle = stdout.lock();
-                if let Err(e) = sru_file.write(handle) {
-                    res = Err(e)
-                }
-            });
-        res
+                sru_file.write(handle, sru_conf.sru_crlf)?;
+            }
+        }
+        Ok(())
     }
 
+    /// Returns the K4 forms of the taxpayer of `--sru-org-num`, or of every `--taxpayer`. Without
+    /// an SRU file config, the forms have no taxpayer. `taxable_trades` are summed and rounded
+    /// per taxpayer.
+    async fn try_sru_file(taxable_trades: &[TaxableTrade], spec: &K4Spec, config: &Config) -> Result<SruFile> {
+        match &config.sru_file_config {
+            Some(sru_conf) if !sru_conf.taxpayers.is_empty() =>
+                Self::try_sru_file_of_taxpayers(taxable_trades, spec, &sru_conf.taxpayers, config).await,
+            Some(sru_conf) =>
+                Self::try_sru_file_of(taxable_trades, spec, sru_conf.sru_org_num.clone(), sru_conf.sru_org_name.clone(), config),
+            None =>
+                Self::try_sru_file_of(taxable_trades, spec, String::new(), None, config),
+        }
+    }
+
+    /// Returns the K4 forms of every taxpayer, with their share of the taxable trades of the
+    /// statement, or of their own statement.
+    pub(crate) async fn try_sru_file_of_taxpayers(
+        taxable_trades: &[TaxableTrade],
+        spec: &K4Spec,
+        taxpayers: &[Taxpayer],
+        config: &Config
+    ) -> Result<SruFile> {
+        let shares: Decimal = taxpayers.iter().filter(|t| t.path.is_none()).map(|t| t.share).sum();
+        if !shares.is_zero() && shares != dec!(1) {
+            warn!("The shares of the statement add up to {}, not 1", shares);
+        }
+
+        let mut forms = vec![];
+        for taxpayer in taxpayers {
+            let taxable_trades = match &taxpayer.path {
+                Some(path) => Self::in_year_traded(crate::read_taxable_trades(path, config).await?, config),
+                None => taxable_trades.to_vec(),
+            };
+            // The share is taken of the unrounded trades, which are then summed and rounded
+            let taxable_trades: Vec<TaxableTrade> = taxable_trades.iter().map(|t| t.scaled(taxpayer.share)).collect();
+            let sru_file = Self::try_sru_file_of(&taxable_trades, spec, taxpayer.org_num.clone(), taxpayer.name.clone(), config)?;
+            forms.extend(sru_file.forms);
+        }
+
+        Ok(SruFile { forms })
+    }
+
+    /// Sums and rounds `taxable_trades` as in the CSV output, and returns the K4 forms of the
+    /// taxpayer `org_num`.
+    fn try_sru_file_of(
+        taxable_trades: &[TaxableTrade],
+        spec: &K4Spec,
+        org_num: String,
+        name: Option<String>,
+        config: &Config
+    ) -> Result<SruFile> {
+        let taxable_trades = Self::summed_and_rounded(taxable_trades.to_vec(), config)?;
+        SruFile::try_new(&taxable_trades, spec, org_num, name)
+    }
+
+    async fn print_sru_diff(
+        taxable_trades: &Vec<TaxableTrade>,
+        path: &std::path::PathBuf,
+        config: &Config
+    ) -> Result<()> {
+        let in_file = SruFile::read(path)?;
+        let first = in_file.forms.first().ok_or(anyhow!("{:?} has no forms", path))?;
+
+        // Without --sru-file, the blankett and taxpayer are taken from the file
+        let (spec, org_num, name) = match &config.sru_file_config {
+            Some(conf) => (K4Spec::for_year(config.income_year)?, conf.sru_org_num.clone(), conf.sru_org_name.clone()),
+            None => (K4Spec::for_blankett(&first.form)?, first.identity.org_num.clone(), first.name.clone()),
+        };
+        let computed = SruFile::try_new(taxable_trades, &spec, org_num, name)?;
+        // INK1 forms in the file are compared with the sums of the computed K4 forms
+        let computed = match in_file.forms.iter().any(|form| !form.is_k4()) {
+            true => computed.with_ink1(&spec, &Ink1Spec::for_year(spec.income_year)?)?,
+            false => computed,
+        };
+
+        // The CSV of the differences is all that goes to stdout, so that it can be piped
+        let diffs = SruDiff::diff(&in_file, &computed);
+        if diffs.is_empty() {
+            eprintln!("No differences: {:?} matches the computed forms.", path);
+        }
+        writer::print_csv_rows(&diffs).await?;
+        Ok(())
+    }
+
+    /// Sums the taxable trades of every currency into one trade per currency, sorted by currency.
     pub(crate) fn try_sum_cash_amount_by_currency(taxable_trades: &Vec<TaxableTrade>) -> Result<Vec<TaxableTrade>> {
-        let mut summary_map: HashMap<Currency, (Decimal, Decimal, Decimal)> = HashMap::new();
+        let mut summary_map: BTreeMap<Currency, (Decimal, Decimal, Decimal, Vec<Estimate>)> = BTreeMap::new();
 
         let mut err = Ok(());
 
         for trade in taxable_trades {
             if let Some(costs) = trade.sum_cash_amount() {
-                let (acc_amount, acc_income, acc_costs) =
+                let (acc_amount, acc_income, acc_costs, acc_estimates) =
                     summary_map.entry(trade.currency.clone())
-                        .or_insert((dec!(0), dec!(0), dec!(0)));
+                        .or_insert((dec!(0), dec!(0), dec!(0), vec![]));
                 *acc_amount += trade.amount;
                 *acc_income += trade.income.amount();
                 *acc_costs += costs;
+                acc_estimates.extend(trade.estimates.iter().cloned());
             } else {
                 err = Err(anyhow!("All costs must be cash"));
             }
@@ -221,19 +504,253 @@ impl TaxableTrade {
 
         let sum =
             summary_map.into_iter()
-                .map(|(currency, (amount, income, costs))|
-                    TaxableTrade::new(
+                .map(|(currency, (amount, income, costs, estimates))| {
+                    let mut sum = TaxableTrade::new(
                         None,
                         currency,
                         amount,
                         Money::new_cash("UNKNOWN".to_string(), income),
                         vec![Money::new_cash("UNKNOWN".to_string(), costs)],
                         Some(income + costs)
-                    )
-                )
+                    );
+                    sum.estimates = estimates;
+                    sum
+                })
                 .collect();
 
         Ok(sum)
     }
 }
 
+#[cfg(test)]
+mod test {
+    use crate::calculator::{Direction, Kind, MissingCost, Money, TaxableTrade, Trade};
+    use crate::skatteverket::{K4Spec, Taxpayer};
+    use crate::Config;
+    use futures::executor::block_on;
+    use rust_decimal_macros::dec;
+    use std::error::Error;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    #[test]
+    fn should_calculate_all_currencies_in_date_order() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let trade = |direction, currency: &str, paid_amount, exchanged_amount, date: &str| Trade {
+            direction,
+            paid_currency: currency.to_string(),
+            paid_amount,
+            exchanged_currency: "SEK".to_string(),
+            exchanged_amount,
+            date: date.to_string(),
+            is_vault: false,
+            paid_fee: dec!(0),
+            exchanged_fee: dec!(0),
+            kind: Kind::Exchange
+        };
+        let trades = vec![
+            trade(Direction::Buy, "EOS", dec!(10), dec!(-100), "2023-01-01 10:00:00"),
+            trade(Direction::Buy, "BTC", dec!(1), dec!(-200000), "2023-01-02 10:00:00"),
+            trade(Direction::Sell, "BTC", dec!(-0.5), dec!(110000), "2023-01-03 10:00:00"),
+            trade(Direction::Sell, "EOS", dec!(-5), dec!(60), "2023-01-04 10:00:00"),
+            trade(Direction::Sell, "BTC", dec!(-0.5), dec!(90000), "2023-01-05 10:00:00"),
+        ];
+
+        /*
+         * When
+         */
+        let taxable_trades = TaxableTrade::taxable_trades_all_currencies(&trades, MissingCost::Abort)?;
+
+        /*
+         * Then
+         */
+        let net_incomes: Vec<_> = taxable_trades.iter().map(|t| (t.currency.as_str(), t.net_income)).collect();
+        assert_eq!(net_incomes, vec![
+            ("BTC", Some(dec!(10000))),
+            ("EOS", Some(dec!(10))),
+            ("BTC", Some(dec!(-10000))),
+        ]);
+
+        /*
+         * When
+         */
+        let trades = vec![
+            trade(Direction::Sell, "EOS", dec!(-5), dec!(60), "2023-01-04 10:00:00"),
+            trade(Direction::Sell, "BTC", dec!(-0.5), dec!(90000), "2023-01-05 10:00:00"),
+        ];
+        let err = TaxableTrade::taxable_trades_all_currencies(&trades, MissingCost::Abort).unwrap_err();
+
+        /*
+         * Then
+         */
+        assert!(err.to_string().contains("in 2 currency pairs"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_leave_out_swaps_unless_estimating_prices() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let trade = |direction, paid_amount, exchanged_currency: &str, exchanged_amount, date: &str| Trade {
+            direction,
+            paid_currency: "DOGE".to_string(),
+            paid_amount,
+            exchanged_currency: exchanged_currency.to_string(),
+            exchanged_amount,
+            date: date.to_string(),
+            is_vault: false,
+            paid_fee: dec!(0),
+            exchanged_fee: dec!(0),
+            kind: Kind::Exchange
+        };
+        let trades = vec![
+            trade(Direction::Buy, dec!(1000), "SEK", dec!(-100), "2021-12-31 17:54:48"),
+            trade(Direction::Sell, dec!(-500), "EOS", dec!(20), "2022-03-01 16:21:49"),
+            trade(Direction::Sell, dec!(-500), "SEK", dec!(80), "2022-04-02 17:22:50"),
+        ];
+        let doge = "DOGE".to_string();
+        let sek = "SEK".to_string();
+
+        /*
+         * When
+         */
+        let default = block_on(TaxableTrade::taxable_trades(&trades, &doge, &sek, false, MissingCost::Abort))?;
+        let with_swaps = block_on(TaxableTrade::taxable_trades(&trades, &doge, &sek, true, MissingCost::Abort))?;
+
+        /*
+         * Then
+         */
+        let default_rows: Vec<_> = default.iter().map(|t| (t.amount, &t.income, &t.costs, t.net_income)).collect();
+        assert_eq!(default_rows, vec![
+            (dec!(-500), &Money::new_cash(sek.clone(), dec!(80)), &vec![Money::new_cash(sek.clone(), dec!(-50))], Some(dec!(30))),
+        ]);
+        assert!(TaxableTrade::try_sum_cash_amount_by_currency(&default).is_ok());
+        assert_eq!(with_swaps.len(), 2);
+        assert_eq!(with_swaps[0].net_income, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_sum_currencies_in_currency_order() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let taxable_trade = |currency: &str, income, cost| TaxableTrade::new(
+            Some("2023-06-01 10:00:00".to_string()),
+            currency.to_string(),
+            dec!(-1),
+            Money::new_cash("SEK".to_string(), income),
+            vec![Money::new_cash("SEK".to_string(), cost)],
+            Some(income + cost),
+        );
+        let taxable_trades = vec![
+            taxable_trade("EOS", dec!(100), dec!(-60)),
+            taxable_trade("DOGE", dec!(50), dec!(-70)),
+            taxable_trade("BTC", dec!(10), dec!(-5)),
+            taxable_trade("EOS", dec!(20), dec!(-10)),
+        ];
+
+        /*
+         * When
+         */
+        let sums = TaxableTrade::try_sum_cash_amount_by_currency(&taxable_trades)?;
+
+        /*
+         * Then
+         */
+        let rows: Vec<_> = sums.iter().map(|t| (t.currency.as_str(), t.amount, t.net_income)).collect();
+        assert_eq!(rows, vec![
+            ("BTC", dec!(-1), Some(dec!(5))),
+            ("DOGE", dec!(-1), Some(dec!(-20))),
+            ("EOS", dec!(-2), Some(dec!(50))),
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_read_the_statement_of_a_taxpayer_with_a_path() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let spec = K4Spec::for_year(2023)?;
+        let mut file = tempfile::NamedTempFile::new()?;
+        writeln!(file, "
+            Type,Product,Started Date,Completed Date,Description,Amount,Currency,Fiat amount,Fiat amount (inc. fees),Fee,Base currency,State,Balance
+            EXCHANGE,Current,2023-01-01 10:00:00,2023-01-01 10:00:00,Exchanged to BTC,1.0000,BTC,1000.00,1010.00,10.00,SEK,COMPLETED,1.0000
+            EXCHANGE,Current,2023-02-01 10:00:00,2023-02-01 10:00:00,Exchanged to SEK,-1.0000,BTC,-1500.00,-1485.00,15.00,SEK,COMPLETED,0.0000
+        ")?;
+        let taxable_trades = vec![
+            TaxableTrade::new(
+                Some("2023-06-01 10:00:00".to_string()),
+                "EOS".to_string(),
+                dec!(-10),
+                Money::new_cash("SEK".to_string(), dec!(100)),
+                vec![Money::new_cash("SEK".to_string(), dec!(-60))],
+                Some(dec!(40)),
+            ),
+        ];
+        let taxpayers = [
+            Taxpayer { org_num: "195012310123".to_string(), name: None, share: dec!(1), path: None },
+            Taxpayer { org_num: "195501014327".to_string(), name: None, share: dec!(0.5), path: Some(PathBuf::from(file.path())) },
+        ];
+        let config = Config::default();
+
+        /*
+         * When
+         */
+        let sru_file = block_on(TaxableTrade::try_sru_file_of_taxpayers(&taxable_trades, &spec, &taxpayers, &config))?;
+
+        /*
+         * Then
+         */
+        let designations: Vec<(&str, &str)> = sru_file.forms.iter()
+            .flat_map(|form| form.information_groups.iter().flat_map(|g| &g.information).map(|info| (form.identity.org_num.as_str(), info)))
+            .filter(|(_, info)| info.field_code == "3411")
+            .map(|(org_num, info)| (org_num, info.field_value.as_str()))
+            .collect();
+        assert_eq!(designations, vec![("195012310123", "EOS"), ("195501014327", "BTC")]);
+        let totals: Vec<_> = sru_file.totals(&spec)?.iter().map(|t| (t.sales_price, t.costs, t.gain, t.loss)).collect();
+        assert_eq!(totals[..2], [
+            (dec!(100), dec!(60), dec!(40), dec!(0)),
+            (dec!(743), dec!(505), dec!(238), dec!(0)),
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_make_the_same_record_as_serialize() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let taxable_trade = TaxableTrade::new(
+            Some("2023-06-01 10:00:00".to_string()),
+            "DOGE".to_string(),
+            dec!(-10),
+            Money::new_cash("SEK".to_string(), dec!(100)),
+            vec![Money::new_cash("SEK".to_string(), dec!(-60.5))],
+            Some(dec!(39.5)),
+        );
+
+        /*
+         * When
+         */
+        let mut wtr = csv::WriterBuilder::new().has_headers(true).from_writer(vec![]);
+        wtr.serialize(&taxable_trade)?;
+        let serialized = String::from_utf8(wtr.into_inner()?)?;
+
+        /*
+         * Then
+         */
+        let expected = format!("{}\n{}\n", TaxableTrade::header().join(","), taxable_trade.to_record().join(","));
+        assert_eq!(serialized, expected);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Days, Months, NaiveDate};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use super::Currency;
+
+/// How often to print the holdings in a timeline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timeline {
+    /// After every trade
+    Events,
+    /// At the end of every day up to the last trade
+    Daily,
+    /// At the end of every month up to the last trade
+    Monthly,
+}
+
+/// The quantity and cost basis in a `CostBook` after a trade.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub(crate) struct Snapshot {
+    #[serde(rename = "Date")]
+    pub(crate) date: String,
+
+    #[serde(rename = "Currency")]
+    pub(crate) currency: Currency,
+
+    #[serde(rename = "Quantity")]
+    pub(crate) quantity: Decimal,
+
+    // Empty when some of the cost is valued in another coin
+    #[serde(rename = "Cost Basis")]
+    pub(crate) basis: Option<Decimal>,
+
+    #[serde(rename = "Average Cost")]
+    pub(crate) average_cost: Option<Decimal>,
+}
+
+impl Timeline {
+    /// Sorts the snapshots by date and currency. For `Daily` and `Monthly`, the last snapshot
+    /// of every currency is carried forward to every day or month up to the last trade, dated
+    /// with the day or month.
+    pub(crate) fn sample(&self, mut snapshots: Vec<Snapshot>) -> Result<Vec<Snapshot>> {
+        snapshots.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.currency.cmp(&b.currency)));
+
+        let (first, last) = match (snapshots.first(), snapshots.last()) {
+            (Some(first), Some(last)) if *self != Timeline::Events => (day_of(first)?, day_of(last)?),
+            _ => return Ok(snapshots),
+        };
+
+        let mut sampled = vec![];
+        let mut latest: BTreeMap<Currency, Snapshot> = BTreeMap::new();
+        let mut snapshots = snapshots.into_iter().peekable();
+        let mut day = self.start_of_period(first);
+        while day <= last {
+            let next = self.next_period(day);
+            let next_period = self.period(next);
+            while let Some(snapshot) = snapshots.next_if(|s| s.date < next_period) {
+                latest.insert(snapshot.currency.clone(), snapshot);
+            }
+            let period = self.period(day);
+            sampled.extend(latest.values().map(|snapshot| Snapshot { date: period.clone(), ..snapshot.clone() }));
+            day = next;
+        }
+        Ok(sampled)
+    }
+
+    fn start_of_period(&self, day: NaiveDate) -> NaiveDate {
+        match self {
+            Timeline::Monthly => day.with_day(1).unwrap_or(day),
+            _ => day,
+        }
+    }
+
+    fn next_period(&self, day: NaiveDate) -> NaiveDate {
+        match self {
+            Timeline::Monthly => day + Months::new(1),
+            _ => day + Days::new(1),
+        }
+    }
+
+    fn period(&self, day: NaiveDate) -> String {
+        match self {
+            Timeline::Monthly => day.format("%Y-%m").to_string(),
+            _ => day.format("%Y-%m-%d").to_string(),
+        }
+    }
+}
+
+/// The day of a snapshot dated `YYYY-MM-DD hh:mm:ss`.
+fn day_of(snapshot: &Snapshot) -> Result<NaiveDate> {
+    snapshot.date.get(.."YYYY-MM-DD".len())
+        .and_then(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok())
+        .ok_or(anyhow!("Invalid date {} of {}", snapshot.date, snapshot.currency))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::calculator::timeline::{Snapshot, Timeline};
+    use rust_decimal_macros::dec;
+    use std::error::Error;
+
+    #[test]
+    fn should_sample_snapshots() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let snapshot = |date: &str, currency: &str, quantity| Snapshot {
+            date: date.to_string(),
+            currency: currency.to_string(),
+            quantity,
+            basis: None,
+            average_cost: None,
+        };
+        let snapshots = vec![
+            snapshot("2023-01-01 10:00:00", "EOS", dec!(10)),
+            snapshot("2023-01-01 12:00:00", "EOS", dec!(20)),
+            snapshot("2023-01-02 10:00:00", "BTC", dec!(1)),
+            snapshot("2023-01-03 10:00:00", "EOS", dec!(5)),
+        ];
+
+        /*
+         * When
+         */
+        let daily = Timeline::Daily.sample(snapshots.clone())?;
+        let monthly = Timeline::Monthly.sample(snapshots)?;
+
+        /*
+         * Then
+         */
+        assert_eq!(daily, vec![
+            snapshot("2023-01-01", "EOS", dec!(20)),
+            snapshot("2023-01-02", "BTC", dec!(1)),
+            snapshot("2023-01-02", "EOS", dec!(20)),
+            snapshot("2023-01-03", "BTC", dec!(1)),
+            snapshot("2023-01-03", "EOS", dec!(5)),
+        ]);
+        assert_eq!(monthly, vec![
+            snapshot("2023-01", "BTC", dec!(1)),
+            snapshot("2023-01", "EOS", dec!(5)),
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_carry_snapshots_forward_over_gaps() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let snapshot = |date: &str, currency: &str, quantity| Snapshot {
+            date: date.to_string(),
+            currency: currency.to_string(),
+            quantity,
+            basis: None,
+            average_cost: None,
+        };
+        let snapshots = vec![
+            snapshot("2023-01-30 10:00:00", "EOS", dec!(10)),
+            snapshot("2023-02-02 10:00:00", "EOS", dec!(5)),
+            snapshot("2023-04-02 10:00:00", "BTC", dec!(1)),
+        ];
+
+        /*
+         * When
+         */
+        let daily = Timeline::Daily.sample(snapshots[..2].to_vec())?;
+        let monthly = Timeline::Monthly.sample(snapshots)?;
+
+        /*
+         * Then
+         */
+        assert_eq!(daily, vec![
+            snapshot("2023-01-30", "EOS", dec!(10)),
+            snapshot("2023-01-31", "EOS", dec!(10)),
+            snapshot("2023-02-01", "EOS", dec!(10)),
+            snapshot("2023-02-02", "EOS", dec!(5)),
+        ]);
+        assert_eq!(monthly, vec![
+            snapshot("2023-01", "EOS", dec!(10)),
+            snapshot("2023-02", "EOS", dec!(5)),
+            snapshot("2023-03", "EOS", dec!(5)),
+            snapshot("2023-04", "BTC", dec!(1)),
+            snapshot("2023-04", "EOS", dec!(5)),
+        ]);
+
+        Ok(())
+    }
+}
@@ -3,7 +3,7 @@ use serde::{Serialize};
 
 use super::{Currency, Money};
 
-#[derive(Debug, PartialEq, Serialize)]
+#[derive(Debug, PartialEq, Clone, Serialize)]
 pub(crate) struct Trade {
     #[serde(rename = "Type")]
     pub(crate) direction: Direction,
@@ -27,6 +27,17 @@ pub(crate) struct Trade {
 
     #[serde(rename = "Vault")]
     pub(crate) is_vault: bool,
+
+    // Fee paid in the paid currency, already deducted from `paid_amount`
+    #[serde(rename = "Paid Fee")]
+    pub(crate) paid_fee: Decimal,
+
+    // Fee paid in the exchanged currency, already deducted from `exchanged_amount`
+    #[serde(rename = "Exchanged Fee")]
+    pub(crate) exchanged_fee: Decimal,
+
+    #[serde(skip)]
+    pub(crate) kind: Kind,
 }
 
 impl Trade {
@@ -38,7 +49,10 @@ impl Trade {
             exchanged_currency: "".to_string(),
             exchanged_amount: Default::default(),
             date: "".to_string(),
-            is_vault: false
+            is_vault: false,
+            paid_fee: Default::default(),
+            exchanged_fee: Default::default(),
+            kind: Kind::Exchange,
         }
     }
 
@@ -51,7 +65,44 @@ impl Trade {
     }
 }
 
-#[derive(Debug, PartialEq, Serialize)]
+/// Inserts `trade` after the last trade in `trades` on or before its date.
+pub(crate) fn insert_by_date(trades: &mut Vec<Trade>, trade: Trade) {
+    let i = trades.iter().rposition(|t| t.date <= trade.date).map_or(0, |i| i + 1);
+    trades.insert(i, trade);
+}
+
+impl std::fmt::Display for Trade {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:?} {} {} for {} {} ({})",
+            self.date,
+            self.direction,
+            self.paid_amount.abs(),
+            self.paid_currency,
+            self.exchanged_amount.abs(),
+            self.exchanged_currency,
+            if self.is_vault { "Savings" } else { "Current" }
+        )
+    }
+}
+
+/// The kind of statement row a trade comes from.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum Kind {
+    Exchange,
+    CardPayment { merchant: String },
+}
+
+impl Kind {
+    /// A card payment to the merchant in the description of the row, e.g. "Payment to Amazon".
+    pub(crate) fn card_payment(description: &str) -> Kind {
+        let merchant = description.strip_prefix("Payment to ").unwrap_or(description);
+        Kind::CardPayment { merchant: merchant.to_string() }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
 pub(crate) enum Direction {
     Buy,
     Sell
@@ -0,0 +1,291 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use super::{lot, Currency, Direction, Kind, TaxableTrade, Trade};
+
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// A hypothetical trade, given as `DIRECTION,AMOUNT,CURRENCY,PRODUCT,PRICE[,DATE]`,
+/// e.g. `sell,0.5,BTC,Savings,350000`. `PRICE` is the price of one unit in the base currency
+/// and `DATE`, `YYYY-MM-DD[ HH:MM:SS]`, defaults to tomorrow.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhatIf {
+    direction: Direction,
+    amount: Decimal,
+    currency: Currency,
+    is_vault: bool,
+    price: Decimal,
+    date: String,
+}
+
+impl FromStr for WhatIf {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let fields: Vec<&str> = s.split(',').map(str::trim).collect();
+        let (direction, amount, currency, product, price, date) =
+            match fields[..] {
+                [direction, amount, currency, product, price] => (direction, amount, currency, product, price, None),
+                [direction, amount, currency, product, price, date] => (direction, amount, currency, product, price, Some(date)),
+                _ => return Err(anyhow!("expected DIRECTION,AMOUNT,CURRENCY,PRODUCT,PRICE[,DATE]")),
+            };
+
+        let direction = match direction.to_lowercase().as_str() {
+            "buy" => Direction::Buy,
+            "sell" => Direction::Sell,
+            _ => return Err(anyhow!("DIRECTION must be 'buy' or 'sell', got '{}'", direction)),
+        };
+
+        let is_vault = match product.to_lowercase().as_str() {
+            "current" => false,
+            "savings" => true,
+            _ => return Err(anyhow!("PRODUCT must be 'Current' or 'Savings', got '{}'", product)),
+        };
+
+        let date = match date {
+            Some(date) => parse_date(date)?,
+            None => (chrono::Local::now() + chrono::Duration::days(1)).format("%Y-%m-%d 12:00:00").to_string(),
+        };
+
+        Ok(WhatIf {
+            direction,
+            amount: Decimal::from_str(amount).context("Invalid AMOUNT")?.abs(),
+            currency: currency.to_uppercase(),
+            is_vault,
+            price: Decimal::from_str(price).context("Invalid PRICE")?.abs(),
+            date,
+        })
+    }
+}
+
+/// Parses `YYYY-MM-DD HH:MM:SS`, or `YYYY-MM-DD` as noon, after the trades earlier that day.
+fn parse_date(date: &str) -> Result<String> {
+    let date_time = match date.len() == "YYYY-MM-DD".len() {
+        true => format!("{} 12:00:00", date),
+        false => date.to_string(),
+    };
+
+    // chrono also accepts e.g. `2023-1-5`, which would not sort among the dates of the trades
+    match NaiveDateTime::parse_from_str(&date_time, DATE_FORMAT) {
+        Ok(parsed) if parsed.format(DATE_FORMAT).to_string() == date_time => Ok(date_time),
+        _ => Err(anyhow!("DATE must be YYYY-MM-DD or YYYY-MM-DD HH:MM:SS, got '{}'", date)),
+    }
+}
+
+impl WhatIf {
+    /// Converts the hypothetical trade into a `Trade` against `base_currency`.
+    pub(crate) fn to_trade(&self, base_currency: &Currency) -> Trade {
+        let total = (self.amount * self.price).round_dp(2);
+        let (paid_amount, exchanged_amount) =
+            match self.direction {
+                Direction::Buy => (self.amount, -total),
+                Direction::Sell => (-self.amount, total),
+            };
+
+        Trade {
+            direction: self.direction.clone(),
+            paid_currency: self.currency.clone(),
+            paid_amount,
+            exchanged_currency: base_currency.clone(),
+            exchanged_amount,
+            date: self.date.clone(),
+            is_vault: self.is_vault,
+            paid_fee: dec!(0),
+            exchanged_fee: dec!(0),
+            kind: Kind::Exchange,
+        }
+    }
+
+    /// Whether `taxable_trade` is the sale of this hypothetical trade.
+    pub(crate) fn is_sale_of(&self, taxable_trade: &TaxableTrade) -> bool {
+        self.direction == Direction::Sell
+            && taxable_trade.currency == self.currency
+            && taxable_trade.amount == -self.amount
+            && taxable_trade.date.as_deref() == Some(self.date.as_str())
+    }
+}
+
+impl std::fmt::Display for WhatIf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:?} {} {} at {} each ({})",
+            self.date, self.direction, self.amount, self.currency, self.price, lot::pool(self.is_vault)
+        )
+    }
+}
+
+/// The net income of every year with taxable trades, without and with the hypothetical trades.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub(crate) struct WhatIfTotal {
+    #[serde(rename = "Year")]
+    pub(crate) year: String,
+
+    #[serde(rename = "Net Income")]
+    pub(crate) net_income: Decimal,
+
+    #[serde(rename = "Net Income With What-If")]
+    pub(crate) what_if_net_income: Decimal,
+
+    #[serde(rename = "Change")]
+    pub(crate) change: Decimal,
+}
+
+impl WhatIfTotal {
+    /// Sums the net income of every year. Errors if the net income of a taxable trade is
+    /// unknown because some of its costs are not cash.
+    pub(crate) fn totals(taxable_trades: &[TaxableTrade], what_if_trades: &[TaxableTrade]) -> Result<Vec<WhatIfTotal>> {
+        let mut totals: BTreeMap<String, (Decimal, Decimal)> = BTreeMap::new();
+        for t in taxable_trades {
+            totals.entry(t.year()).or_default().0 += net_income(t)?;
+        }
+        for t in what_if_trades {
+            totals.entry(t.year()).or_default().1 += net_income(t)?;
+        }
+
+        let totals = totals.into_iter()
+            .map(|(year, (net_income, what_if_net_income))|
+                WhatIfTotal {
+                    year,
+                    net_income,
+                    what_if_net_income,
+                    change: what_if_net_income - net_income,
+                }
+            )
+            .collect();
+
+        Ok(totals)
+    }
+}
+
+fn net_income(t: &TaxableTrade) -> Result<Decimal> {
+    t.net_income.ok_or(anyhow!("All costs must be cash, but {:?} {} has costs in another coin", t.date, t.currency))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::calculator::{MissingCost, Money, TaxableTrade, Trade, Direction, Kind, WhatIf};
+    use crate::calculator::trade::insert_by_date;
+    use crate::calculator::what_if::WhatIfTotal;
+    use rust_decimal_macros::dec;
+    use std::error::Error;
+
+    #[test]
+    fn should_simulate_sale() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let trades = vec![
+            Trade {
+                direction: Direction::Buy,
+                paid_currency: "BTC".to_string(),
+                paid_amount: dec!(1),
+                exchanged_currency: "SEK".to_string(),
+                exchanged_amount: dec!(-200000),
+                date: "2023-01-02 10:00:00".to_string(),
+                is_vault: true,
+                paid_fee: dec!(0),
+                exchanged_fee: dec!(0),
+                kind: Kind::Exchange
+            },
+        ];
+        let what_if: WhatIf = "sell,0.5,BTC,Savings,350000,2023-06-01".parse()?;
+
+        /*
+         * When
+         */
+        let mut what_if_trades = trades.clone();
+        insert_by_date(&mut what_if_trades, what_if.to_trade(&"SEK".to_string()));
+        let before = TaxableTrade::taxable_trades_all_currencies(&trades, MissingCost::Abort)?;
+        let after = TaxableTrade::taxable_trades_all_currencies(&what_if_trades, MissingCost::Abort)?;
+
+        /*
+         * Then
+         */
+        let sales: Vec<&TaxableTrade> = after.iter().filter(|t| what_if.is_sale_of(t)).collect();
+        assert_eq!(sales.len(), 1);
+        assert_eq!(sales[0].net_income, Some(dec!(75000)));
+        assert_eq!(WhatIfTotal::totals(&before, &after)?, vec![
+            WhatIfTotal {
+                year: "2023".to_string(),
+                net_income: dec!(0),
+                what_if_net_income: dec!(75000),
+                change: dec!(75000),
+            }
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_reject_an_unknown_product() {
+        /*
+         * When
+         */
+        let what_if = "sell,0.5,BTC,Vault,350000".parse::<WhatIf>();
+
+        /*
+         * Then
+         */
+        assert_eq!(what_if.unwrap_err().to_string(), "PRODUCT must be 'Current' or 'Savings', got 'Vault'");
+    }
+
+    #[test]
+    fn should_sell_at_noon_of_a_date_without_time() -> Result<(), Box<dyn Error>> {
+        /*
+         * When
+         */
+        let what_if: WhatIf = "sell,0.5,BTC,Savings,350000,2023-06-01".parse()?;
+
+        /*
+         * Then
+         */
+        assert_eq!(what_if.to_trade(&"SEK".to_string()).date, "2023-06-01 12:00:00");
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_reject_a_date_without_leading_zeros() {
+        /*
+         * When
+         */
+        let what_if = "sell,0.5,BTC,Savings,350000,2023-1-5".parse::<WhatIf>();
+
+        /*
+         * Then
+         */
+        assert_eq!(what_if.unwrap_err().to_string(), "DATE must be YYYY-MM-DD or YYYY-MM-DD HH:MM:SS, got '2023-1-5'");
+    }
+
+    #[test]
+    fn should_not_total_net_income_of_costs_in_another_coin() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let swapped = TaxableTrade::new(
+            Some("2023-06-01 10:00:00".to_string()),
+            "DOGE".to_string(),
+            dec!(-10),
+            Money::new_cash("SEK".to_string(), dec!(100)),
+            vec![Money::new_coupon("EOS".to_string(), dec!(-2), "2023-05-05 05:01:12".to_string())],
+            None,
+        );
+
+        /*
+         * When
+         */
+        let totals = WhatIfTotal::totals(&[], &[swapped]);
+
+        /*
+         * Then
+         */
+        assert!(totals.unwrap_err().to_string().starts_with("All costs must be cash"));
+
+        Ok(())
+    }
+}
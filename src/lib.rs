@@ -1,5 +1,7 @@
 use anyhow::Result;
 use log::info;
+use rust_decimal::Decimal;
+use std::future::Future;
 use std::path::PathBuf;
 use std::time::Instant;
 
@@ -8,9 +10,13 @@ mod reader;
 mod writer;
 mod skatteverket;
 
-use self::calculator::TaxableTrade;
-use self::reader::{RevolutRow2022, RevolutRow2023};
+use self::calculator::{insert_by_date, with_fee_disposals, CardSpending, CostBook, CurrencyErrors, FeeReport, Holding, InsufficientCost, Price, PriceBook, Spread, SpreadYear, TaxableTrade, Trade, WhatIfTotal};
+pub use self::calculator::{GroupBy, MissingCost, Timeline, WhatIf};
+pub use self::skatteverket::Taxpayer;
+use self::reader::{verify_balances, BalanceRow, PriceRow, RevolutRow2022, RevolutRow2023, VerifiedBalances};
+use self::skatteverket::{Harvest, Rounding};
 
+#[derive(Default)]
 pub struct Config {
     pub path: PathBuf,
     pub currency: String,
@@ -21,11 +27,80 @@ pub struct Config {
     pub year_traded: Option<u16>,
     pub sum: bool,
     pub csv_version: u16,
+    pub estimate_prices: bool,
+    pub missing_cost: MissingCost,
+    pub explain: bool,
+    pub tax_estimate: bool,
+    pub other_capital_income: Decimal,
+    pub holdings: bool,
+    pub prices: Vec<(String, Decimal)>,
+    pub price_file: Option<PathBuf>,
+    pub timeline: Option<Timeline>,
+    pub what_if: Vec<WhatIf>,
+    pub yearly: bool,
+    pub harvest: bool,
+    pub print_fees: bool,
+    pub fee_disposals: bool,
+    pub spread: bool,
+    pub card_spending: bool,
+    pub group_by: Vec<GroupBy>,
+    pub table: bool,
+    pub verify_balances: bool,
+    pub diff_sru: Option<PathBuf>,
+    pub income_year: u16,
+    pub print_sru_totals: bool,
+    pub print_rounding: bool,
+    pub ink1: bool,
+    pub round: bool,
 }
 
+impl Config {
+    /// The flags that change the figures of an SRU file, for its #SYSTEMINFO. Paths and the
+    /// personnummer of the taxpayers are left out, as the input files are recorded by hash.
+    pub(crate) fn sru_options(&self) -> Vec<String> {
+        let mut options = vec![format!("--csv-version {}", self.csv_version)];
+        if self.csv_version == 2022 {
+            options.push(format!("--currency {}", self.currency));
+        }
+        options.push(format!("--income-year {}", self.income_year));
+        if let Some(year) = self.year_traded {
+            options.push(format!("--year-traded {}", year));
+        }
+        if self.missing_cost != MissingCost::Abort {
+            options.push(format!("--missing-cost {}", format!("{:?}", self.missing_cost).to_lowercase()));
+        }
+        options.extend(self.prices.iter().map(|(currency, price)| format!("--price {}={}", currency, price)));
+        options.extend(
+            [
+                (self.sum, "--sum"),
+                (self.round, "--round"),
+                (self.estimate_prices, "--estimate-prices"),
+                (self.sru_file_config.as_ref().is_some_and(|c| c.sru_ink1), "--sru-ink1"),
+            ]
+            .into_iter()
+            .filter(|(chosen, _)| *chosen)
+            .map(|(_, flag)| flag.to_string())
+        );
+        if let Some(sru_conf) = &self.sru_file_config {
+            options.extend(sru_conf.taxpayers.iter().map(|t| format!("--taxpayer share={}", t.share)));
+        }
+        options
+    }
+}
+
+#[derive(Default)]
 pub struct SruFileConfig {
     pub sru_org_num: String,
     pub sru_org_name: Option<String>,
+    pub sru_org_address: Option<String>,
+    pub sru_org_postcode: Option<String>,
+    pub sru_org_city: Option<String>,
+    pub sru_org_email: Option<String>,
+    pub sru_dir: Option<PathBuf>,
+    pub sru_crlf: bool,
+    pub sru_ink1: bool,
+    pub sru_timestamp: Option<chrono::NaiveDateTime>,
+    pub taxpayers: Vec<Taxpayer>,
 }
 
 /// Reads the transactions with type `Exchange` from the path and prints the results to
@@ -83,21 +158,62 @@ pub async fn merge_exchanges(path: &PathBuf, currency: &String) -> Result<()> {
 /// calculates tax from the transactions,
 /// and finally prints the results to `std::io::stdout()`.
 pub async fn calculate_tax_v2022(config: &Config) -> Result<()> {
+    let result = run(config, RevolutRow2022::read_balance_rows).await;
+    with_transfers(result, config).await
+}
+
+pub async fn calculate_tax_v2023(config: &Config) -> Result<()> {
+    let result = run(config, RevolutRow2023::read_balance_rows).await;
+    with_transfers(result, config).await
+}
+
+/// Runs the report chosen in `config` on the trades of the statement, with
+/// `read_balance_rows` reading the balances of the statement for `--verify-balances`.
+async fn run<'a, F>(config: &'a Config, read_balance_rows: impl FnOnce(&'a PathBuf) -> F) -> Result<()>
+where
+    F: Future<Output = std::io::Result<Vec<BalanceRow>>>,
+{
+    if config.verify_balances {
+        let rows = read_balance_rows(&config.path).await?;
+        return print_verified_balances(rows.len(), verify_balances(&rows)?).await;
+    }
+
     let now = Instant::now();
-    let rows = RevolutRow2022::read_exchanges_in_currency(&config.path, &config.currency).await?;
+    let trades = read_trades(&config.path, config).await?;
     info!("Done reading csv file. Elapsed: {:.2?}", now.elapsed());
 
-    let now = Instant::now();
-    let trades = RevolutRow2022::rows_to_trades(&rows, &config.currency).await?;
-    info!("Done converting to transactions. Elapsed: {:.2?}", now.elapsed());
+    if config.print_fees {
+        return print_fees(&trades).await;
+    }
+
+    if config.spread {
+        return print_spread(&trades, config).await;
+    }
+
+    if config.card_spending {
+        return print_card_spending(&trades, config).await;
+    }
+
+    let trades = if config.fee_disposals { with_fee_disposals(trades) } else { trades };
+
+    if config.holdings {
+        return print_holdings(&trades, config).await;
+    }
+
+    if config.harvest {
+        return print_harvest(&trades, config).await;
+    }
+
+    if let Some(timeline) = config.timeline {
+        return print_timeline(&trades, config, timeline).await;
+    }
+
+    if !config.what_if.is_empty() {
+        return print_what_if(&trades, config).await;
+    }
 
     let now = Instant::now();
-    let taxable_trades =
-        TaxableTrade::taxable_trades(
-            &trades,
-            &config.currency,
-            &config.base_currency
-        ).await?;
+    let taxable_trades = calculate_taxable_trades(&trades, &config.path, config).await?;
     info!("Done calculating taxes. Elapsed: {:.2?}", now.elapsed());
 
     let now = Instant::now();
@@ -107,18 +223,290 @@ pub async fn calculate_tax_v2022(config: &Config) -> Result<()> {
     Ok(())
 }
 
-pub async fn calculate_tax_v2023(config: &Config) -> Result<()> {
+/// Replays the trades and prints the quantity and cost basis left in every currency and pool,
+/// valued at the prices given with `--price`, or else the latest prices in `--price-file`.
+async fn print_holdings(trades: &[Trade], config: &Config) -> Result<()> {
+    let prices = read_price_book(config).await?;
+
     let now = Instant::now();
-    let trades = RevolutRow2023::deserialize_from(&config.path).await?;
-    info!("Done reading csv file. Elapsed: {:.2?}", now.elapsed());
+    let holdings: Vec<Holding> =
+        CostBook::replay_all(trades, config.missing_cost)?
+            .iter()
+            .flat_map(|(book, _)| book.holdings())
+            .map(|holding| holding.valued_at(&prices))
+            .collect();
+    info!("Done calculating holdings. Elapsed: {:.2?}", now.elapsed());
+
+    writer::print_csv_rows(&holdings).await?;
 
+    Ok(())
+}
+
+/// Replays the trades and prints the quantity, cost basis and average cost of every currency
+/// after every trade, or at the end of every day or month up to the last trade.
+async fn print_timeline(trades: &[Trade], config: &Config, timeline: Timeline) -> Result<()> {
+    let now = Instant::now();
+    let snapshots = CostBook::timeline_all(trades, config.missing_cost)?;
+    let snapshots = timeline.sample(snapshots)?;
+    info!("Done calculating timeline. Elapsed: {:.2?}", now.elapsed());
+
+    writer::print_csv_rows(&snapshots).await?;
+
+    Ok(())
+}
+
+/// Calculates the taxable trades with and without the hypothetical trades in `--what-if`,
+/// then prints the taxable trades of the hypothetical sales and the net income of every year.
+async fn print_what_if(trades: &[Trade], config: &Config) -> Result<()> {
     let now = Instant::now();
-    let taxable_trades = TaxableTrade::taxable_trades_all_currencies(&trades).await;
+    let mut what_if_trades = trades.to_vec();
+    for what_if in &config.what_if {
+        info!("What if: {}", what_if);
+        insert_by_date(&mut what_if_trades, what_if.to_trade(&config.base_currency));
+    }
+
+    let taxable_trades = calculate_taxable_trades(trades, &config.path, config).await?;
+    let what_if_taxable_trades = calculate_taxable_trades(&what_if_trades, &config.path, config).await?;
     info!("Done calculating taxes. Elapsed: {:.2?}", now.elapsed());
 
+    if config.yearly {
+        let totals = WhatIfTotal::totals(&taxable_trades, &what_if_taxable_trades)?;
+        writer::print_csv_rows(&totals).await?;
+    } else {
+        let sales: Vec<&TaxableTrade> =
+            what_if_taxable_trades.iter()
+                .filter(|t| config.what_if.iter().any(|w| w.is_sale_of(t)))
+                .collect();
+        writer::print_csv_rows(&sales).await?;
+    }
+
+    Ok(())
+}
+
+/// Reads another statement at `path` in the same way as the one in `config`, and calculates
+/// its taxable trades.
+pub(crate) async fn read_taxable_trades(path: &PathBuf, config: &Config) -> Result<Vec<TaxableTrade>> {
+    let trades = read_trades(path, config).await?;
+    let trades = if config.fee_disposals { with_fee_disposals(trades) } else { trades };
+
+    calculate_taxable_trades(&trades, path, config).await
+}
+
+/// Reads the trades of the statement at `path`, of `--currency` for a 2022 statement.
+async fn read_trades(path: &PathBuf, config: &Config) -> Result<Vec<Trade>> {
+    let trades = match config.csv_version {
+        2022 => {
+            let rows = RevolutRow2022::read_exchanges_in_currency(path, &config.currency).await?;
+            RevolutRow2022::rows_to_trades(&rows, &config.currency).await?
+        },
+        _ => RevolutRow2023::deserialize_from(path).await?,
+    };
+    Ok(trades)
+}
+
+/// Calculates the taxable trades of the trades read from the statement at `path`. With
+/// `--estimate-prices`, the costs of the 2022 crypto swaps are valued with the prices in the
+/// statement.
+async fn calculate_taxable_trades(trades: &[Trade], path: &PathBuf, config: &Config) -> Result<Vec<TaxableTrade>> {
+    match config.csv_version {
+        2022 => {
+            let mut taxable_trades =
+                TaxableTrade::taxable_trades(
+                    trades,
+                    &config.currency,
+                    &config.base_currency,
+                    config.estimate_prices,
+                    config.missing_cost
+                ).await?;
+
+            if config.estimate_prices {
+                let now = Instant::now();
+                let prices = RevolutRow2022::read_prices(path, &config.base_currency).await?;
+                let prices = PriceBook::new(prices);
+                taxable_trades.iter_mut()
+                    .for_each(|t| t.estimate_coupons(&prices, &config.base_currency));
+                info!("Done estimating prices. Elapsed: {:.2?}", now.elapsed());
+            }
+
+            Ok(taxable_trades)
+        },
+        _ => TaxableTrade::taxable_trades_all_currencies(trades, config.missing_cost),
+    }
+}
+
+/// Replays the trades and prints the holdings that are valued below their cost basis, with the
+/// loss that selling them would realise and its effect on the tax of the year.
+async fn print_harvest(trades: &[Trade], config: &Config) -> Result<()> {
+    let prices = read_price_book(config).await?;
+
     let now = Instant::now();
-    TaxableTrade::print_taxable_trades(taxable_trades, config).await?;
-    info!("Done printing results. Elapsed: {:.2?}", now.elapsed());
+    let year = config.year_traded
+        .map(|year| year.to_string())
+        .unwrap_or(chrono::Local::now().format("%Y").to_string());
+    let books = CostBook::replay_all(trades, config.missing_cost)?;
+
+    let net_incomes: Vec<Decimal> =
+        books.iter()
+            .flat_map(|(_, taxable_trades)| taxable_trades)
+            .filter(|t| t.year() == year)
+            .filter_map(Rounding::try_new)
+            .map(|rounding| rounding.rounded_net_income)
+            .collect();
+
+    let harvests: Vec<Harvest> =
+        books.iter()
+            .flat_map(|(book, _)| book.holdings())
+            .map(|holding| holding.valued_at(&prices))
+            .filter_map(|holding| Harvest::try_new(&holding, &net_incomes, config.other_capital_income))
+            .collect();
+    info!("Done calculating harvests. Elapsed: {:.2?}", now.elapsed());
+
+    writer::print_csv_rows(&harvests).await?;
+
+    Ok(())
+}
+
+/// Reads the prices in `--price-file` and adds the prices given with `--price` as today's prices.
+async fn read_price_book(config: &Config) -> Result<PriceBook> {
+    let now = Instant::now();
+    let mut prices = match &config.price_file {
+        Some(path) => PriceRow::read_prices(path).await?,
+        None => vec![],
+    };
+    let today = chrono::Local::now().naive_local();
+    prices.extend(
+        config.prices.iter()
+            .map(|(currency, price)|
+                Price {
+                    currency: currency.clone(),
+                    date: today,
+                    price: *price,
+                    source: "--price".to_string(),
+                }
+            )
+    );
+    info!("Done reading prices. Elapsed: {:.2?}", now.elapsed());
+    Ok(PriceBook::new(prices))
+}
+
+/// Prints the fees paid by year and currency.
+async fn print_fees(trades: &[Trade]) -> Result<()> {
+    let report = FeeReport::report(trades);
+    writer::print_csv_rows(&report).await?;
+    Ok(())
+}
+
+/// Prints the spread cost of every exchange compared with the reference prices in
+/// `--price-file`, or with `--yearly` the spread costs and the net income of every year.
+async fn print_spread(trades: &[Trade], config: &Config) -> Result<()> {
+    let prices = read_price_book(config).await?;
+
+    let now = Instant::now();
+    let spreads = Spread::spreads(trades, &prices, &config.base_currency)?;
+    info!("Done calculating spreads. Elapsed: {:.2?}", now.elapsed());
+
+    match config.yearly {
+        true => {
+            let taxable_trades = calculate_taxable_trades(trades, &config.path, config).await?;
+            writer::print_csv_rows(&SpreadYear::years(&spreads, &taxable_trades)?).await?
+        },
+        false => writer::print_csv_rows(&spreads).await?,
+    }
+
+    Ok(())
+}
 
+/// Prints the crypto spent with the card by month and merchant, with the net income it realised.
+async fn print_card_spending(trades: &[Trade], config: &Config) -> Result<()> {
+    let taxable_trades = calculate_taxable_trades(trades, &config.path, config).await?;
+    writer::print_csv_rows(&CardSpending::report(&taxable_trades, &config.base_currency)?).await?;
     Ok(())
 }
+
+async fn print_verified_balances(rows: usize, verified: usize) -> Result<()> {
+    writer::print_csv_rows(&[VerifiedBalances { rows, verified }]).await?;
+    Ok(())
+}
+
+/// Lists the transfers in the statement before a sale without enough costs in its error, as a
+/// transfer in is an acquisition that the statement has no cost for.
+async fn with_transfers(result: Result<()>, config: &Config) -> Result<()> {
+    let mut err = match result {
+        Ok(()) => return Ok(()),
+        Err(err) => err,
+    };
+
+    let transfers = match config.csv_version {
+        2022 => RevolutRow2022::read_transfers(&config.path).await,
+        _ => RevolutRow2023::read_transfers(&config.path).await,
+    };
+    if let Ok(transfers) = transfers {
+        add_transfers(&mut err, &transfers);
+    }
+
+    Err(err)
+}
+
+fn add_transfers(err: &mut anyhow::Error, transfers: &[BalanceRow]) {
+    if let Some(insufficient_cost) = err.downcast_mut::<InsufficientCost>() {
+        insufficient_cost.transfers =
+            transfers.iter()
+                .filter(|t| t.currency == insufficient_cost.currency && t.date < insufficient_cost.date)
+                .map(|t| t.to_string())
+                .collect();
+    } else if let Some(CurrencyErrors(errors)) = err.downcast_mut::<CurrencyErrors>() {
+        errors.iter_mut().for_each(|(_, err)| add_transfers(err, transfers));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::calculator::{CurrencyErrors, InsufficientCost};
+    use crate::reader::BalanceRow;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn should_add_transfers_before_the_sale() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let insufficient_cost = InsufficientCost {
+            date: "2023-03-01 10:00:00".to_string(),
+            currency: "EOS".to_string(),
+            sold: dec!(50),
+            short: dec!(20),
+            current: dec!(30),
+            savings: dec!(0),
+            preceding: vec![],
+            transfers: vec![],
+        };
+        let mut err: anyhow::Error = CurrencyErrors(vec![(("EOS".to_string(), "SEK".to_string()), insufficient_cost.into())]).into();
+        let transfer = |date: &str, currency: &str| BalanceRow {
+            date: date.to_string(),
+            currency: currency.to_string(),
+            product: "Current".to_string(),
+            description: "Transfer from wallet".to_string(),
+            amount: dec!(20),
+            balance: None,
+        };
+
+        /*
+         * When
+         */
+        super::add_transfers(&mut err, &[
+            transfer("2023-02-01 10:00:00", "EOS"),
+            transfer("2023-02-01 10:00:00", "BTC"),
+            transfer("2023-04-01 10:00:00", "EOS"),
+        ]);
+
+        /*
+         * Then
+         */
+        let message = err.to_string();
+        assert!(message.contains("Transfers of EOS in the statement before the sale:\n    2023-02-01 10:00:00 Transfer from wallet 20 EOS (Current), balance -"));
+        assert!(!message.contains("BTC"));
+        assert!(!message.contains("2023-04-01"));
+
+        Ok(())
+    }
+}
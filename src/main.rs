@@ -1,4 +1,5 @@
 use anyhow::{anyhow, Context, Result};
+use chrono::Datelike;
 use clap::Parser;
 use futures::executor::block_on;
 use log::error;
@@ -22,6 +23,78 @@ struct Cli {
     #[arg(long, help = "(2022 csv only) Merge two lines of a currency exchange into a single trade, and print to stdout")]
     print_trades: bool,
 
+    #[arg(long, help = "(2022 csv only) Value crypto-to-crypto trades in the base currency, using the prices implied by the closest trades of the same coins against the base currency")]
+    estimate_prices: bool,
+
+    #[arg(long, value_enum, help = "What to do when a sale has not enough costs to deduct from. Defaults to 'abort'")]
+    missing_cost: Option<MissingCostArg>,
+
+    #[arg(long, help = "Print the acquisitions, pool, quantity and cost of every lot deducted by the taxable trades")]
+    explain: bool,
+
+    #[arg(long, help = "Estimate the Swedish tax on the taxable trades and print the figures for INK1")]
+    tax_estimate: bool,
+
+    #[arg(long, allow_hyphen_values = true, help = "Other capital income in the base currency to include in --tax-estimate, negative for a loss. Defaults to 0")]
+    other_capital_income: Option<rust_decimal::Decimal>,
+
+    #[arg(long, help = "Print the quantity, average cost and cost basis left in every currency and pool, and their market value when a price is given")]
+    holdings: bool,
+
+    #[arg(long, value_name = "CURRENCY=PRICE", value_parser = parse_price, help = "Price of one unit of a currency in the base currency for --holdings and --harvest, e.g. BTC=350000. Can be given several times")]
+    price: Vec<(String, rust_decimal::Decimal)>,
+
+    #[arg(long, help = "Path to a CSV file with the columns Date,Currency,Price. --holdings and --harvest use the latest price of each currency, --spread the price closest in time to each exchange")]
+    price_file: Option<std::path::PathBuf>,
+
+    #[arg(long, value_enum, help = "Print the quantity, cost basis and average cost of every currency after every trade, or at the end of every day or month up to the last trade")]
+    timeline: Option<TimelineArg>,
+
+    #[arg(long, value_name = "DIRECTION,AMOUNT,CURRENCY,PRODUCT,PRICE[,DATE]", help = "Add a hypothetical trade, e.g. 'sell,0.5,BTC,Savings,350000', and print its taxable trade and how it changes the net income of the year. PRICE is per unit in the base currency and DATE defaults to tomorrow. Can be given several times")]
+    what_if: Vec<revolutax::WhatIf>,
+
+    #[arg(long, help = "With --what-if, print the net income of every year without and with the hypothetical trades instead of the taxable trades of the hypothetical sales. With --spread, print the volume, spread cost and net income of every year instead of every exchange")]
+    yearly: bool,
+
+    #[arg(long, help = "Print the holdings valued below their cost basis with --price or --price-file, and how selling them would change the tax of the year (--year-traded, defaults to this year)")]
+    harvest: bool,
+
+    #[arg(long, help = "Print the fees paid by year and currency")]
+    print_fees: bool,
+
+    #[arg(long, help = "Report fees paid in the traded cryptocurrency as disposals of their own, instead of deducting them from the traded quantity")]
+    fee_disposals: bool,
+
+    #[arg(long, help = "Compare the price of every exchange with the closest reference price in --price-file, and print the spread cost of every exchange, or of every year with --yearly")]
+    spread: bool,
+
+    #[arg(long, help = "Print the crypto spent with the card by month and merchant, and the gain or loss the purchases realised")]
+    card_spending: bool,
+
+    #[arg(long, value_enum, value_delimiter = ',', help = "Sum the income, cost and net income of the taxable trades by one or more of currency, month, quarter, year and product, e.g. --group-by quarter,currency")]
+    group_by: Vec<GroupByArg>,
+
+    #[arg(long, help = "Print the taxable trades, or the groups of --group-by, as a table instead of CSV")]
+    table: bool,
+
+    #[arg(long, help = "Add up the rows of every type by currency and product, and check the running balance against the Balance column. Stops at the first row that differs")]
+    verify_balances: bool,
+
+    #[arg(long, value_name = "PATH", help = "Compare an SRU file, e.g. one submitted last year, field by field with the K4 forms computed from the statement")]
+    diff_sru: Option<std::path::PathBuf>,
+
+    #[arg(long, help = "Print the sums of sales prices, costs, gains and losses of every K4 form, and of all forms, as carried over to INK1")]
+    print_sru_totals: bool,
+
+    #[arg(long, help = "Print the income, cost and net income of every taxable trade before and after rounding to whole kronor, and the rounding differences per trade and in total")]
+    print_rounding: bool,
+
+    #[arg(long, help = "Print what to enter in INK1 7.4 and 8.3 in the e-service: the gains on the K4 forms, and 70% of the losses, for every taxpayer")]
+    ink1: bool,
+
+    #[arg(long, help = "Round the income and cost of the taxable trades to whole kronor, as in the SRU file, and take the net income as their difference")]
+    round: bool,
+
     #[arg(long, help = "Print taxable trades in the Swedish Tax Agency's SRU file format")]
     sru_file: bool,
 
@@ -31,6 +104,36 @@ struct Cli {
     #[arg(long, help = "Name to print in the SRU file")]
     sru_org_name: Option<String>,
 
+    #[arg(long, help = "Street address to print in the INFO.SRU file")]
+    sru_org_address: Option<String>,
+
+    #[arg(long, help = "Postcode to print in the INFO.SRU file")]
+    sru_org_postcode: Option<String>,
+
+    #[arg(long, help = "City to print in the INFO.SRU file")]
+    sru_org_city: Option<String>,
+
+    #[arg(long, help = "Email address to print in the INFO.SRU file")]
+    sru_org_email: Option<String>,
+
+    #[arg(long, help = "Write BLANKETTER.SRU and INFO.SRU to this directory instead of printing the SRU file. Implies --sru-file")]
+    sru_dir: Option<std::path::PathBuf>,
+
+    #[arg(long, help = "Income year of the K4 blankett in the SRU file. Defaults to --year-traded, or to last year")]
+    income_year: Option<u16>,
+
+    #[arg(long, help = "End the lines of the SRU files with CRLF instead of LF")]
+    sru_crlf: bool,
+
+    #[arg(long, help = "Add an INK1 blankett with the gains (7.4) and deductible losses (8.3) of every taxpayer to the SRU file")]
+    sru_ink1: bool,
+
+    #[arg(long, value_name = "ORGNUM,NAME,SHARE[,PATH]", help = "A taxpayer to write K4 forms for in the same SRU file, with their share of the taxable trades in the statement, or in their own statement at PATH, e.g. 195012310123,Svea Specimen,50%. Can be given several times. Implies --sru-file")]
+    taxpayer: Vec<revolutax::Taxpayer>,
+
+    #[arg(long, value_parser = parse_timestamp, help = "Date and time of the SRU file, e.g. 2024-04-28T22:20:30, instead of now. Makes the output reproducible")]
+    sru_timestamp: Option<chrono::NaiveDateTime>,
+
     #[arg(long, help = "Summarize taxable trades by currency (\"genomsnittsmetoden\")")]
     sum: bool,
 
@@ -41,26 +144,137 @@ struct Cli {
     csv_version: Option<u16>,
 }
 
+/// The values of `--missing-cost`.
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum MissingCostArg {
+    /// Stop the calculation with an error
+    Abort,
+    /// Report the part of the sale without costs with a zero cost
+    Zero,
+    /// Report 20% of the sale price as the cost of the part of the sale without costs ("schablonmetoden")
+    Schablon,
+}
+
+impl From<MissingCostArg> for revolutax::MissingCost {
+    fn from(arg: MissingCostArg) -> Self {
+        match arg {
+            MissingCostArg::Abort => revolutax::MissingCost::Abort,
+            MissingCostArg::Zero => revolutax::MissingCost::Zero,
+            MissingCostArg::Schablon => revolutax::MissingCost::Schablon,
+        }
+    }
+}
+
+/// The values of `--timeline`.
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum TimelineArg {
+    /// After every trade
+    Events,
+    /// At the end of every day up to the last trade
+    Daily,
+    /// At the end of every month up to the last trade
+    Monthly,
+}
+
+impl From<TimelineArg> for revolutax::Timeline {
+    fn from(arg: TimelineArg) -> Self {
+        match arg {
+            TimelineArg::Events => revolutax::Timeline::Events,
+            TimelineArg::Daily => revolutax::Timeline::Daily,
+            TimelineArg::Monthly => revolutax::Timeline::Monthly,
+        }
+    }
+}
+
+/// The values of `--group-by`.
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum GroupByArg {
+    Currency,
+    Month,
+    Quarter,
+    Year,
+    /// Current or Savings
+    Product,
+}
+
+impl From<GroupByArg> for revolutax::GroupBy {
+    fn from(arg: GroupByArg) -> Self {
+        match arg {
+            GroupByArg::Currency => revolutax::GroupBy::Currency,
+            GroupByArg::Month => revolutax::GroupBy::Month,
+            GroupByArg::Quarter => revolutax::GroupBy::Quarter,
+            GroupByArg::Year => revolutax::GroupBy::Year,
+            GroupByArg::Product => revolutax::GroupBy::Product,
+        }
+    }
+}
+
 impl Cli {
-    fn to_config(self) -> Result<revolutax::Config> {
+    fn into_config(self) -> Result<revolutax::Config> {
         let Cli {
             path,
             currency,
             base_currency,
             print_exchanges_only,
             print_trades,
+            estimate_prices,
+            missing_cost,
+            explain,
+            tax_estimate,
+            other_capital_income,
+            holdings,
+            price,
+            price_file,
+            timeline,
+            what_if,
+            yearly,
+            harvest,
+            print_fees,
+            fee_disposals,
+            spread,
+            card_spending,
+            group_by,
+            table,
+            verify_balances,
+            diff_sru,
+            print_sru_totals,
+            print_rounding,
+            ink1,
+            round,
             sru_file,
             sru_org_num,
             sru_org_name,
+            sru_org_address,
+            sru_org_postcode,
+            sru_org_city,
+            sru_org_email,
+            sru_dir,
+            income_year,
+            sru_crlf,
+            sru_ink1,
+            sru_timestamp,
+            taxpayer,
             sum,
             year_traded,
             csv_version,
         } = self;
 
-        let sru_file_config = if sru_file {
+        let sru_file_config = if sru_file || sru_dir.is_some() || !taxpayer.is_empty() {
+            // With --taxpayer, the first taxpayer sends the file unless --sru-org-num is given
+            let sender = taxpayer.first().filter(|_| sru_org_num.is_none());
             Some(revolutax::SruFileConfig {
-                sru_org_num: sru_org_num.ok_or(anyhow!("--sru_org_num <SRU_ORG_NUM> is mandatory if --sru_file is given"))?,
-                sru_org_name,
+                sru_org_num: sru_org_num.or(sender.map(|t| t.org_num().to_string()))
+                    .ok_or(anyhow!("--sru_org_num <SRU_ORG_NUM> is mandatory if --sru_file is given"))?,
+                sru_org_name: sru_org_name.or(sender.and_then(|t| t.name().map(str::to_string))),
+                sru_org_address,
+                sru_org_postcode,
+                sru_org_city,
+                sru_org_email,
+                sru_dir,
+                sru_crlf,
+                sru_ink1,
+                sru_timestamp,
+                taxpayers: taxpayer,
             })
         } else {
             None
@@ -76,17 +290,52 @@ impl Cli {
             year_traded,
             sum,
             csv_version: csv_version.unwrap_or(2023),
+            estimate_prices,
+            missing_cost: missing_cost.map(Into::into).unwrap_or(revolutax::MissingCost::Abort),
+            explain,
+            tax_estimate,
+            other_capital_income: other_capital_income.unwrap_or_default(),
+            holdings,
+            prices: price,
+            price_file,
+            timeline: timeline.map(Into::into),
+            what_if,
+            yearly,
+            harvest,
+            print_fees,
+            fee_disposals,
+            spread,
+            card_spending,
+            group_by: group_by.into_iter().map(Into::into).collect(),
+            table,
+            verify_balances,
+            diff_sru,
+            income_year: income_year.or(year_traded).unwrap_or(chrono::Utc::now().year() as u16 - 1),
+            print_sru_totals,
+            print_rounding,
+            ink1,
+            round,
         };
 
         Ok(config)
     }
 }
 
+fn parse_price(s: &str) -> Result<(String, rust_decimal::Decimal)> {
+    let (currency, price) = s.split_once('=').ok_or(anyhow!("expected CURRENCY=PRICE"))?;
+    Ok((currency.to_string(), price.parse()?))
+}
+
+fn parse_timestamp(s: &str) -> Result<chrono::NaiveDateTime> {
+    chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S"))
+        .map_err(|_| anyhow!("expected YYYY-MM-DDTHH:MM:SS"))
+}
 
 fn main() {
     env_logger::init();
     let args = Cli::parse();
-    let config = args.to_config().with_context(|| format!("Invalid command line flags")).unwrap();
+    let config = args.into_config().context("Invalid command line flags").unwrap();
 
     match (config.csv_version, config.print_exchanges_only, config.print_trades) {
         (2022, true, _) => {
@@ -0,0 +1,149 @@
+use anyhow::Result;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::calculator::Currency;
+
+/// A statement row reduced to what changes the balance of a currency in a product.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct BalanceRow {
+    pub(crate) date: String,
+    pub(crate) currency: Currency,
+    pub(crate) product: String,
+    pub(crate) description: String,
+    // The change of the balance, including fees paid in the currency
+    pub(crate) amount: Decimal,
+    pub(crate) balance: Option<Decimal>,
+}
+
+impl std::fmt::Display for BalanceRow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} ({}), balance {}",
+            self.date,
+            self.description,
+            self.amount,
+            self.currency,
+            self.product,
+            self.balance.map(|b| b.to_string()).unwrap_or("-".to_string())
+        )
+    }
+}
+
+/// The first row where the running balance differs from the `Balance` column.
+#[derive(Debug, PartialEq)]
+pub(crate) struct BalanceDivergence {
+    pub(crate) row: BalanceRow,
+    pub(crate) computed: Decimal,
+    pub(crate) preceding: Vec<BalanceRow>,
+}
+
+impl std::fmt::Display for BalanceDivergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Balance of {} in {} diverges on {}: the statement says {}, the rows add up to {}.",
+            self.row.currency,
+            self.row.product,
+            self.row.date,
+            self.row.balance.unwrap_or_default(),
+            self.computed
+        )?;
+        if !self.preceding.is_empty() {
+            writeln!(f, "Preceding rows in {} ({}):", self.row.currency, self.row.product)?;
+            for row in &self.preceding {
+                writeln!(f, "    {}", row)?;
+            }
+        }
+        writeln!(f, "Diverging row:")?;
+        writeln!(f, "    {}", self.row)?;
+        write!(f, "Rows may be missing, e.g. when the export starts after the first transaction, or a row type is not handled.")
+    }
+}
+
+impl std::error::Error for BalanceDivergence {}
+
+/// The result of `verify_balances` over a statement.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct VerifiedBalances {
+    #[serde(rename = "Rows")]
+    pub(crate) rows: usize,
+
+    // The rows with a `Balance` that matches the running balance
+    #[serde(rename = "Verified")]
+    pub(crate) verified: usize,
+}
+
+/// Keeps a running balance per currency and product over `rows`, in order, and checks it against
+/// the `Balance` of every row that has one. Returns the number of rows checked.
+pub(crate) fn verify_balances(rows: &[BalanceRow]) -> Result<usize> {
+    let mut running: HashMap<(&Currency, &String), (Decimal, Vec<&BalanceRow>)> = HashMap::new();
+    let mut checked = 0;
+
+    for row in rows {
+        let (balance, preceding) = running.entry((&row.currency, &row.product)).or_default();
+        *balance += row.amount;
+
+        if let Some(expected) = row.balance {
+            if expected != *balance {
+                return Err(BalanceDivergence {
+                    row: row.clone(),
+                    computed: *balance,
+                    preceding: preceding.iter().rev().take(5).rev().map(|r| (*r).clone()).collect(),
+                }.into());
+            }
+            checked += 1;
+        }
+        preceding.push(row);
+    }
+
+    Ok(checked)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::reader::balance::{verify_balances, BalanceDivergence, BalanceRow};
+    use rust_decimal_macros::dec;
+    use std::error::Error;
+
+    #[test]
+    fn should_find_first_divergence() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let row = |date: &str, product: &str, amount, balance| BalanceRow {
+            date: date.to_string(),
+            currency: "EOS".to_string(),
+            product: product.to_string(),
+            description: "".to_string(),
+            amount,
+            balance,
+        };
+        let rows = vec![
+            row("2023-01-01 10:00:00", "Current", dec!(30), Some(dec!(30))),
+            row("2023-01-02 10:00:00", "Savings", dec!(10), Some(dec!(10))),
+            row("2023-01-03 10:00:00", "Current", dec!(-10), None),
+            row("2023-01-04 10:00:00", "Current", dec!(5), Some(dec!(25))),
+            row("2023-01-05 10:00:00", "Current", dec!(5), Some(dec!(35))),
+        ];
+
+        /*
+         * When
+         */
+        let checked = verify_balances(&rows[..4])?;
+        let err = verify_balances(&rows).unwrap_err();
+
+        /*
+         * Then
+         */
+        assert_eq!(checked, 3);
+        let divergence = err.downcast_ref::<BalanceDivergence>().unwrap();
+        assert_eq!(divergence.row, rows[4]);
+        assert_eq!(divergence.computed, dec!(30));
+        assert_eq!(divergence.preceding, vec![rows[0].clone(), rows[2].clone(), rows[3].clone()]);
+
+        Ok(())
+    }
+}
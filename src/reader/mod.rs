@@ -1,5 +1,9 @@
+mod balance;
+mod price_file;
 mod revolut_row_2022;
 mod revolut_row_2023;
 
+pub(crate) use balance::{verify_balances, BalanceRow, VerifiedBalances};
+pub(crate) use price_file::PriceRow;
 pub(crate) use revolut_row_2022::RevolutRow2022;
 pub(crate) use revolut_row_2023::RevolutRow2023;
@@ -0,0 +1,148 @@
+use anyhow::{anyhow, Result};
+use csv::{ReaderBuilder, Trim};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+use crate::calculator::price_book::parse_date;
+use crate::calculator::{Currency, Price};
+
+/// A row in a price file with the columns `Date,Currency,Price`, where `Price` is the price
+/// of one unit of `Currency` in the base currency.
+#[derive(Debug, Deserialize, PartialEq)]
+pub(crate) struct PriceRow {
+    #[serde(rename = "Date")]
+    date: String,
+
+    #[serde(rename = "Currency")]
+    currency: Currency,
+
+    #[serde(rename = "Price")]
+    price: Decimal,
+}
+
+impl PriceRow {
+    pub(crate) async fn read_prices(path: &PathBuf) -> Result<Vec<Price>> {
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(true)
+            .delimiter(b',')
+            .trim(Trim::All)
+            .flexible(true)
+            .from_path(path)?;
+
+        // Lines of only whitespace are skipped, every other row must be a price
+        let headers = rdr.headers()?.clone();
+        let prices =
+            rdr.records()
+                .filter(|record| !matches!(record, Ok(record) if record.iter().all(str::is_empty)))
+                .map(|record| {
+                    let record = record.map_err(|e| match e.position() {
+                        Some(position) => anyhow!("Invalid price on line {} of {:?}: {}", position.line(), path, e),
+                        None => anyhow!("Invalid price in {:?}: {}", path, e),
+                    })?;
+                    let line = record.position().map(|position| position.line()).unwrap_or_default();
+                    let row: PriceRow = record.deserialize(Some(&headers))
+                        .map_err(|e| anyhow!("Invalid price on line {} of {:?}: {}", line, path, e))?;
+                    let date = parse_date(&row.date)
+                        .ok_or(anyhow!("Invalid date on line {} of {:?}: expected YYYY-MM-DD or YYYY-MM-DD HH:MM:SS, got '{}'", line, path, row.date))?;
+                    Ok(Price {
+                        currency: row.currency,
+                        date,
+                        price: row.price,
+                        source: format!("{:?}", path),
+                    })
+                })
+                .collect::<Result<Vec<Price>>>()?;
+
+        Ok(prices)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::calculator::PriceBook;
+    use crate::reader::PriceRow;
+    use futures::executor::block_on;
+    use rust_decimal_macros::dec;
+    use std::error::Error;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn should_read_prices() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "
+            Date,Currency,Price
+            2023-12-31,EOS,7.25
+            2023-12-30,EOS,7.10
+            2023-12-31,BTC,425000
+        ")?;
+
+        /*
+         * When
+         */
+        let prices = block_on(PriceRow::read_prices(&file.path().to_path_buf()))?;
+        let prices = PriceBook::new(prices);
+
+        /*
+         * Then
+         */
+        assert_eq!(prices.latest(&"EOS".to_string()).map(|p| p.price), Some(dec!(7.25)));
+        assert_eq!(prices.latest(&"BTC".to_string()).map(|p| p.price), Some(dec!(425000)));
+        assert_eq!(prices.latest(&"ETH".to_string()), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_report_the_line_of_an_invalid_price() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "Date,Currency,Price")?;
+        writeln!(file, "2023-12-31,EOS,7.25")?;
+        writeln!(file, "2023-12-31,BTC,four hundred")?;
+
+        /*
+         * When
+         */
+        let err = block_on(PriceRow::read_prices(&file.path().to_path_buf())).unwrap_err();
+
+        /*
+         * Then
+         */
+        assert!(err.to_string().starts_with("Invalid price on line 3 of "), "{}", err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_report_the_line_of_an_invalid_date() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "Date,Currency,Price")?;
+        writeln!(file, "2023-12-30,EOS,7.10")?;
+        writeln!(file, "31/12/2023,EOS,7.25")?;
+
+        /*
+         * When
+         */
+        let err = block_on(PriceRow::read_prices(&file.path().to_path_buf())).unwrap_err();
+
+        /*
+         * Then
+         */
+        assert_eq!(err.to_string(), format!(
+            "Invalid date on line 3 of {:?}: expected YYYY-MM-DD or YYYY-MM-DD HH:MM:SS, got '31/12/2023'",
+            file.path()
+        ));
+
+        Ok(())
+    }
+}
@@ -2,11 +2,14 @@ use csv::{ReaderBuilder, Trim};
 use log::{debug, info};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use std::io::Result;
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind, Result};
 use std::ops::Neg;
 use std::path::PathBuf;
 
-use crate::calculator::{Currency, Direction, Trade};
+use crate::calculator::price_book::parse_date;
+use crate::calculator::{Currency, Direction, Kind, Price, Trade};
+use super::BalanceRow;
 
 #[derive(Debug, Deserialize, Serialize, PartialEq)]
 pub(crate) struct RevolutRow2022 {
@@ -95,6 +98,42 @@ impl RevolutRow2022 {
         Ok(rows)
     }
 
+    /// Reads the completed rows of every type, oldest first, with the change and the balance of
+    /// every row. Fees are included in the change. 2022 statements have one balance per currency.
+    pub(crate) async fn read_balance_rows(path: &PathBuf) -> Result<Vec<BalanceRow>> {
+        let mut rows: Vec<RevolutRow2022> =
+            Self::deserialize_from(path).await?
+                .into_iter()
+                .filter(|row| row.state == State::Completed)
+                .rev()
+                .collect();
+        rows.sort_by(|a, b| a.completed_date.cmp(&b.completed_date));
+
+        Ok(rows.into_iter().map(RevolutRow2022::into_balance_row).collect())
+    }
+
+    /// Reads the completed rows of type `Transfer`, which are not trades and carry no cost.
+    pub(crate) async fn read_transfers(path: &PathBuf) -> Result<Vec<BalanceRow>> {
+        let rows =
+            Self::deserialize_from(path).await?
+                .into_iter()
+                .filter(|row| row.state == State::Completed && row.r#type == Type::Transfer)
+                .map(RevolutRow2022::into_balance_row)
+                .collect();
+        Ok(rows)
+    }
+
+    fn into_balance_row(self) -> BalanceRow {
+        BalanceRow {
+            date: self.completed_date.unwrap_or(self.started_date),
+            currency: self.currency,
+            product: "Current".to_string(),
+            description: self.description,
+            amount: self.amount + self.fee,
+            balance: self.balance,
+        }
+    }
+
     /// Reads the file from path into a `Vec<Row>`, returns only rows with type `Exchange`.
     pub(crate) async fn read_exchanges(path: &PathBuf) -> Result<Vec<RevolutRow2022>> {
         let rows = Self::deserialize_from(path).await?
@@ -120,7 +159,7 @@ impl RevolutRow2022 {
     }
 
     /// Converts `Vec<Row>` into `Vec<Trade>`, given a target currency.
-    pub(crate) async fn rows_to_trades(rows: &Vec<RevolutRow2022>, currency: &Currency) -> Result<Vec<Trade>> {
+    pub(crate) async fn rows_to_trades(rows: &[RevolutRow2022], currency: &Currency) -> Result<Vec<Trade>> {
         let (trades, _): (Vec<Trade>, Option<&RevolutRow2022>) =
             rows.iter().rev()
                 .fold((vec![], None), |(mut acc, prev), row| {
@@ -147,6 +186,41 @@ impl RevolutRow2022 {
         Ok(trades)
     }
 
+    /// Reads the file from path and returns the prices implied by every exchange between
+    /// `base_currency` and another currency.
+    pub(crate) async fn read_prices(path: &PathBuf, base_currency: &Currency) -> Result<Vec<Price>> {
+        let rows = Self::read_exchanges(path).await?;
+        Self::rows_to_prices(&rows, base_currency)
+    }
+
+    /// An exchange is two rows with the same started date. When one of them is in
+    /// `base_currency`, the price of the other currency is the ratio between their amounts.
+    fn rows_to_prices(rows: &[RevolutRow2022], base_currency: &Currency) -> Result<Vec<Price>> {
+        let mut exchanges: BTreeMap<&String, Vec<&RevolutRow2022>> = BTreeMap::new();
+        rows.iter()
+            .filter(|row| row.r#type == Type::Exchange && row.state == State::Completed)
+            .for_each(|row| exchanges.entry(&row.started_date).or_default().push(row));
+
+        exchanges.into_values()
+            .filter_map(|exchange| {
+                let base = exchange.iter().find(|row| row.currency.eq(base_currency))?;
+                let other = exchange.iter().find(|row| !row.currency.eq(base_currency))?;
+                if exchange.len() != 2 || other.amount.is_zero() {
+                    return None;
+                }
+                let price = parse_date(&other.started_date)
+                    .map(|date| Price {
+                        currency: other.currency.clone(),
+                        date,
+                        price: (base.amount / other.amount).abs(),
+                        source: format!("{} {}, {}", other.started_date, base.description, other.description),
+                    })
+                    .ok_or(Error::new(ErrorKind::InvalidData, format!("Invalid started date '{}'", other.started_date)));
+                Some(price)
+            })
+            .collect()
+    }
+
     fn to_trade(&self, trade: Option<Trade>, currency: &Currency) -> Trade {
         let mut trade = trade.unwrap_or(Trade::new());
 
@@ -166,6 +240,7 @@ impl RevolutRow2022 {
             debug!("{:?}: Bought {:?} of {:?} ({:?}), incl. fee {:?}", self.started_date, self.amount+self.fee, self.currency, self.description, self.fee);
             trade.direction = Direction::Buy;
             trade.paid_amount = self.amount + self.fee;
+            trade.paid_fee = self.fee.neg().normalize();
             trade.paid_currency = currency.clone();
             trade.date = self.started_date.clone();
 
@@ -176,6 +251,7 @@ impl RevolutRow2022 {
             debug!("{:?}: Sold {:?} of {:?} ({:?}), incl. fee {:?}", self.started_date, self.amount+self.fee, self.currency, self.description, self.fee);
             trade.direction = Direction::Sell;
             trade.paid_amount = self.amount + self.fee;
+            trade.paid_fee = self.fee.neg().normalize();
             trade.paid_currency = currency.clone();
             trade.date = self.started_date.clone();
         }
@@ -184,6 +260,7 @@ impl RevolutRow2022 {
             debug!("{:?}: Income of selling is the price of {:?} of {:?} in SEK ({:?}), incl. fee {:?}", self.started_date, self.amount+self.fee, self.currency, self.description, self.fee);
             trade.direction = Direction::Sell;
             trade.exchanged_amount = self.amount + self.fee;
+            trade.exchanged_fee = self.fee.neg().normalize();
             trade.exchanged_currency = self.currency.clone();
         }
         // target currency: "BCH", currency: "SEK", description: "Exchanged to BCH"
@@ -191,6 +268,7 @@ impl RevolutRow2022 {
             debug!("{:?}: Cost of buying is the price of {:?} of {:?} in SEK ({:?}), incl. fee {:?}", self.started_date, self.amount+self.fee, self.currency, self.description, self.fee);
             trade.direction = Direction::Buy;
             trade.exchanged_amount = self.amount + self.fee;
+            trade.exchanged_fee = self.fee.neg().normalize();
             trade.exchanged_currency = self.currency.clone();
         }
         if self.description.contains("Vault") {
@@ -203,17 +281,21 @@ impl RevolutRow2022 {
         // settled_amount: Some(543.21), settled_currency: Some("SEK"), state: Completed, balance: Some(0.00000000) }
         trade.direction = Direction::Sell;
         trade.paid_amount = self.amount + self.fee;
+        trade.paid_fee = self.fee.neg().normalize();
         trade.paid_currency = currency.clone();
         trade.exchanged_amount = self.original_amount.neg();
         trade.exchanged_currency = self.original_currency.clone();
         trade.date = self.started_date.clone();
         trade.is_vault = false;
+        trade.kind = Kind::card_payment(&self.description);
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::calculator::trade::{Direction, Trade};
+    use crate::calculator::Price;
+    use crate::calculator::price_book::parse_date;
+    use crate::calculator::trade::{Direction, Kind, Trade};
     use crate::reader::revolut_row_2022::{RevolutRow2022, State, Type};
     use futures::executor::block_on;
     use rust_decimal_macros::dec;
@@ -466,7 +548,10 @@ mod test {
             exchanged_currency: "SEK".to_string(),
             exchanged_amount: dec!(-300),
             date: "2021-11-10 17:03:13".to_string(),
-            is_vault: true
+            is_vault: true,
+            paid_fee: dec!(0.06),
+            exchanged_fee: dec!(0),
+            kind: Kind::Exchange
         }));
         assert_eq!(iter.next(), Some(Trade {
             direction: Direction::Buy,
@@ -475,7 +560,10 @@ mod test {
             exchanged_currency: "SEK".to_string(),
             exchanged_amount: dec!(-20),
             date: "2021-11-11 18:03:13".to_string(),
-            is_vault: true
+            is_vault: true,
+            paid_fee: dec!(0.06),
+            exchanged_fee: dec!(0),
+            kind: Kind::Exchange
         }));
         assert_eq!(iter.next(), Some(Trade {
             direction: Direction::Buy,
@@ -484,7 +572,10 @@ mod test {
             exchanged_currency: "SEK".to_string(),
             exchanged_amount: dec!(-5080.60),
             date: "2021-12-31 17:54:48".to_string(),
-            is_vault: false
+            is_vault: false,
+            paid_fee: dec!(0),
+            exchanged_fee: dec!(80.15),
+            kind: Kind::Exchange
         }));
         assert_eq!(iter.next(), Some(Trade {
             direction: Direction::Sell,
@@ -493,7 +584,10 @@ mod test {
             exchanged_currency: "EOS".to_string(),
             exchanged_amount: dec!(50),
             date: "2022-03-01 16:21:49".to_string(),
-            is_vault: false
+            is_vault: false,
+            paid_fee: dec!(20.36495977),
+            exchanged_fee: dec!(0),
+            kind: Kind::Exchange
         }));
         assert_eq!(iter.next(), Some(Trade {
             direction: Direction::Sell,
@@ -502,10 +596,68 @@ mod test {
             exchanged_currency: "SEK".to_string(),
             exchanged_amount: dec!(321.23456789),
             date: "2022-04-02 17:22:50".to_string(),
-            is_vault: false
+            is_vault: false,
+            paid_fee: dec!(0),
+            exchanged_fee: dec!(0),
+            kind: Kind::CardPayment { merchant: "Klarna".to_string() }
         }));
         assert_eq!(iter.next(), None);
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn should_parse_prices_from_rows() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let row = |started_date: &str, description: &str, amount, currency: &str| RevolutRow2022 {
+            r#type: Type::Exchange,
+            started_date: started_date.to_string(),
+            completed_date: Some(started_date.to_string()),
+            description: description.to_string(),
+            amount,
+            fee: dec!(0),
+            currency: currency.to_string(),
+            original_amount: amount,
+            original_currency: currency.to_string(),
+            settled_amount: None,
+            settled_currency: None,
+            state: State::Completed,
+            balance: None
+        };
+        let rows = vec![
+            row("2022-03-01 16:21:49", "Exchanged to EOS", dec!(-900.90603463), "DOGE"),
+            row("2022-03-01 16:21:49", "Exchanged from DOGE", dec!(50), "EOS"),
+            row("2022-02-28 10:00:00", "Exchanged to SEK", dec!(-10), "EOS"),
+            row("2022-02-28 10:00:00", "Exchanged from EOS", dec!(245.5), "SEK"),
+            row("2021-12-31 17:54:48", "Exchanged to DOGE", dec!(-5000), "SEK"),
+            row("2021-12-31 17:54:48", "Exchanged from SEK", dec!(2000), "DOGE"),
+        ];
+
+        /*
+         * When
+         */
+        let prices = RevolutRow2022::rows_to_prices(&rows, &"SEK".to_string())?;
+
+        /*
+         * Then
+         */
+        let mut iter = prices.into_iter();
+        assert_eq!(iter.next(), Some(Price {
+            currency: "DOGE".to_string(),
+            date: parse_date("2021-12-31 17:54:48").unwrap(),
+            price: dec!(2.5),
+            source: "2021-12-31 17:54:48 Exchanged to DOGE, Exchanged from SEK".to_string(),
+        }));
+        assert_eq!(iter.next(), Some(Price {
+            currency: "EOS".to_string(),
+            date: parse_date("2022-02-28 10:00:00").unwrap(),
+            price: dec!(24.55),
+            source: "2022-02-28 10:00:00 Exchanged from EOS, Exchanged to SEK".to_string(),
+        }));
+        assert_eq!(iter.next(), None);
+
+        Ok(())
+    }
+}
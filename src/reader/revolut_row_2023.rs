@@ -6,7 +6,8 @@ use std::io::Result;
 use std::ops::Neg;
 use std::path::PathBuf;
 
-use crate::calculator::{Currency, Direction, Trade};
+use crate::calculator::{Currency, Direction, Kind, Trade};
+use super::BalanceRow;
 
 #[derive(Debug, Deserialize, Serialize, PartialEq)]
 pub(crate) struct RevolutRow2023 {
@@ -78,6 +79,44 @@ enum Product {
 
 impl RevolutRow2023 {
     pub(crate) async fn deserialize_from(path: &PathBuf) -> Result<Vec<Trade>> {
+        let rows = Self::read_rows(path).await?;
+        Self::rows_to_trades(&rows).await
+    }
+
+    /// Reads the completed rows of every type, with the change and the balance of every row.
+    pub(crate) async fn read_balance_rows(path: &PathBuf) -> Result<Vec<BalanceRow>> {
+        let rows =
+            Self::read_rows(path).await?
+                .into_iter()
+                .filter(|row| row.state == State::Completed)
+                .map(RevolutRow2023::into_balance_row)
+                .collect();
+        Ok(rows)
+    }
+
+    /// Reads the completed rows of type `TRANSFER`, which are not trades and carry no cost.
+    pub(crate) async fn read_transfers(path: &PathBuf) -> Result<Vec<BalanceRow>> {
+        let rows =
+            Self::read_rows(path).await?
+                .into_iter()
+                .filter(|row| row.state == State::Completed && row.r#type == Type::Transfer)
+                .map(RevolutRow2023::into_balance_row)
+                .collect();
+        Ok(rows)
+    }
+
+    fn into_balance_row(self) -> BalanceRow {
+        BalanceRow {
+            date: self.completed_date.unwrap_or(self.started_date),
+            currency: self.currency,
+            product: format!("{:?}", self.product),
+            description: self.description,
+            amount: self.amount,
+            balance: self.balance,
+        }
+    }
+
+    async fn read_rows(path: &PathBuf) -> Result<Vec<RevolutRow2023>> {
         let now = std::time::Instant::now();
         let mut rdr = ReaderBuilder::new()
             .has_headers(true)
@@ -95,21 +134,20 @@ impl RevolutRow2023 {
         info!("reader::deserialize done. Elapsed: {:.2?}", now.elapsed());
 
         // 2023 Revolut csv is sorted first by Product (Current/Savings), then by date
-        rows.sort_unstable_by(|a,b| a.completed_date.cmp(&b.completed_date));
+        rows.sort_by(|a,b| a.completed_date.cmp(&b.completed_date));
 
-        Self::rows_to_trades(&rows).await
+        Ok(rows)
     }
 
-    async fn rows_to_trades(rows: &Vec<RevolutRow2023>) -> Result<Vec<Trade>> {
+    async fn rows_to_trades(rows: &[RevolutRow2023]) -> Result<Vec<Trade>> {
         let trades: Vec<Trade> =
             rows.iter()
                 .fold(vec![], |mut acc, row| {
                     match row.r#type {
                         Type::Exchange | Type::CardPayment => {
-                            row.to_trade()
-                                .map(|trade|
-                                    acc.push(trade)
-                                );
+                            if let Some(trade) = row.to_trade() {
+                                acc.push(trade);
+                            }
                             acc
                         }
                         _ => acc
@@ -131,12 +169,17 @@ impl RevolutRow2023 {
         trade.paid_amount = self.amount;
         trade.paid_currency  = self.currency.clone();
         trade.exchanged_amount = self.fiat_amount_inc_fees.neg();
+        trade.exchanged_fee = self.fee;
         trade.exchanged_currency = self.base_currency.clone();
 
         if self.product.eq(&Product::Savings) {
             trade.is_vault = true;
         }
 
+        if self.r#type.eq(&Type::CardPayment) {
+            trade.kind = Kind::card_payment(&self.description);
+        }
+
         Some(trade)
     }
 }
@@ -144,8 +187,8 @@ impl RevolutRow2023 {
 #[cfg(test)]
 mod test {
     use crate::calculator::money::Money;
-    use crate::calculator::TaxableTrade;
-    use crate::calculator::trade::{Direction, Trade};
+    use crate::calculator::{Lot, MissingCost, TaxableTrade};
+    use crate::calculator::trade::{Direction, Kind, Trade};
     use crate::reader::RevolutRow2023;
     use futures::executor::block_on;
     use rust_decimal_macros::dec;
@@ -198,7 +241,10 @@ mod test {
             exchanged_currency: "SEK".to_string(),
             exchanged_amount: dec!(-609.15),
             date: "2023-01-01 10:00:00".to_string(),
-            is_vault: false
+            is_vault: false,
+            paid_fee: dec!(0),
+            exchanged_fee: dec!(9.15),
+            kind: Kind::Exchange
         }));
         assert_eq!(iter.next(), Some(&Trade {
             direction: Direction::Sell,
@@ -207,7 +253,10 @@ mod test {
             exchanged_currency: "SEK".to_string(),
             exchanged_amount: dec!(394.86),
             date: "2023-01-02 10:00:00".to_string(),
-            is_vault: false
+            is_vault: false,
+            paid_fee: dec!(0),
+            exchanged_fee: dec!(5.14),
+            kind: Kind::Exchange
         }));
         assert_eq!(iter.next(), Some(&Trade {
             direction: Direction::Buy,
@@ -216,7 +265,10 @@ mod test {
             exchanged_currency: "SEK".to_string(),
             exchanged_amount: dec!(-1009.65),
             date: "2023-02-01 12:00:00".to_string(),
-            is_vault: false
+            is_vault: false,
+            paid_fee: dec!(0),
+            exchanged_fee: dec!(9.65),
+            kind: Kind::Exchange
         }));
         // assert_eq!(iter.next(), None);
 
@@ -224,7 +276,7 @@ mod test {
          * When
          */
         let taxable_trades = block_on(
-            TaxableTrade::taxable_trades(&trades, &"EOS".to_string(), &"SEK".to_string())
+            TaxableTrade::taxable_trades(&trades, &"EOS".to_string(), &"SEK".to_string(), false, MissingCost::Abort)
         )?;
 
         /*
@@ -238,7 +290,14 @@ mod test {
             Money::new_cash("SEK".to_string(), dec!(394.86)),
             vec![Money::new_cash("SEK".to_string(), dec!(-609.15))],
             Some(dec!(-214.29))
-        )));
+        ).with_lots(vec![
+            Lot {
+                acquired: vec!["2023-01-01 10:00:00".to_string()],
+                is_vault: false,
+                quantity: dec!(30),
+                cost: Money::new_cash("SEK".to_string(), dec!(-609.15)),
+            }
+        ])));
         assert_eq!(iter.next(), Some(TaxableTrade::new(
             Some("2023-04-04 11:00:00".to_string()),
             "EOS".to_string(),
@@ -246,7 +305,14 @@ mod test {
             Money::new_cash("SEK".to_string(), dec!(594.86)),
             vec![Money::new_cash("SEK".to_string(), dec!(-1009.65))],
             Some(dec!(-414.79))
-        )));
+        ).with_lots(vec![
+            Lot {
+                acquired: vec!["2023-02-01 12:00:00".to_string()],
+                is_vault: false,
+                quantity: dec!(50),
+                cost: Money::new_cash("SEK".to_string(), dec!(-1009.65)),
+            }
+        ])));
         assert_eq!(iter.next(), Some(TaxableTrade::new(
             Some("2023-05-06 10:00:00".to_string()),
             "EOS".to_string(),
@@ -254,9 +320,50 @@ mod test {
             Money::new_cash("SEK".to_string(), dec!(495.75)),
             vec![Money::new_cash("SEK".to_string(), dec!(-505.72))],
             Some(dec!(-9.97))
-        )));
+        ).with_lots(vec![
+            Lot {
+                acquired: vec![
+                    "2023-03-01 14:00:00".to_string(),
+                    "2023-03-02 14:00:00".to_string(),
+                    "2023-03-03 14:00:00".to_string(),
+                    "2023-03-04 14:00:00".to_string(),
+                ],
+                is_vault: true,
+                quantity: dec!(25),
+                cost: Money::new_cash("SEK".to_string(), dec!(-505.72)),
+            }
+        ]).with_kind(Kind::card_payment("Payment to Amazon"))));
         assert_eq!(iter.next(), None);
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn should_keep_the_file_order_of_rows_completed_at_the_same_time() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "Type,Product,Started Date,Completed Date,Description,Amount,Currency,Fiat amount,Fiat amount (inc. fees),Fee,Base currency,State,Balance")?;
+        // Sorted by product, then by date, as in the statement. Every Current row has a Savings
+        // row completed at the same time
+        for (product, amount) in [("Current", 1), ("Savings", 2)] {
+            for day in 1..=28 {
+                writeln!(file, "EXCHANGE,{},2023-02-{:02} 10:00:00,2023-02-{:02} 10:00:00,Exchanged to EOS,{},EOS,10.00,10.00,0.00,SEK,COMPLETED,0.0000", product, day, day, amount)?;
+            }
+        }
+
+        /*
+         * When
+         */
+        let trades = block_on(RevolutRow2023::deserialize_from(&file.path().to_path_buf()))?;
+
+        /*
+         * Then
+         */
+        let products: Vec<bool> = trades.iter().map(|t| t.is_vault).collect();
+        assert_eq!(products, [false, true].repeat(28));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use anyhow::{anyhow, Result};
+use std::io::Write;
+
+/// Writes `text` encoded as ISO-8859-1, as Skatteverket expects SRU files to be, with `\r\n`
+/// line endings when `crlf` is set. Fails on characters that ISO-8859-1 cannot represent.
+pub(crate) fn write_latin1(text: &str, crlf: bool, mut handle: impl Write) -> Result<()> {
+    let mut bytes = Vec::with_capacity(text.len());
+
+    for (i, line) in text.lines().enumerate() {
+        for c in line.chars() {
+            // ISO-8859-1 is the first 256 code points of Unicode
+            let byte = u8::try_from(u32::from(c))
+                .map_err(|_| anyhow!("Line {}: '{}' cannot be represented in ISO-8859-1: {}", i + 1, c, line))?;
+            bytes.push(byte);
+        }
+        match crlf {
+            true => bytes.extend_from_slice(b"\r\n"),
+            false => bytes.push(b'\n'),
+        }
+    }
+
+    handle.write_all(&bytes)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::skatteverket::encoding::write_latin1;
+
+    #[test]
+    fn should_write_latin1() -> anyhow::Result<()> {
+        /*
+         * When
+         */
+        let mut lf = vec![];
+        write_latin1("#NAMN Åsa Öström\n#FIL_SLUT\n", false, &mut lf)?;
+
+        /*
+         * Then
+         */
+        assert_eq!(lf, b"#NAMN \xC5sa \xD6str\xF6m\n#FIL_SLUT\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_write_latin1_with_crlf() -> anyhow::Result<()> {
+        /*
+         * When
+         */
+        let mut crlf = vec![];
+        write_latin1("#NAMN Åsa Öström\n#FIL_SLUT\n", true, &mut crlf)?;
+
+        /*
+         * Then
+         */
+        assert_eq!(crlf, b"#NAMN \xC5sa \xD6str\xF6m\r\n#FIL_SLUT\r\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_report_the_line_of_a_character_outside_latin1() {
+        /*
+         * When
+         */
+        let err = write_latin1("#FIL_SLUT\n#NAMN Łukasz\n", false, vec![]).unwrap_err();
+
+        /*
+         * Then
+         */
+        assert_eq!(err.to_string(), "Line 2: 'Ł' cannot be represented in ISO-8859-1: #NAMN Łukasz");
+    }
+}
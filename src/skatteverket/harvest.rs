@@ -0,0 +1,120 @@
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::Serialize;
+
+use crate::calculator::{Currency, Holding};
+use super::{round_kronor, TaxEstimate};
+
+/// A holding valued below its cost basis, and what selling it would do to the tax of the year.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct Harvest {
+    #[serde(rename = "Currency")]
+    pub(crate) currency: Currency,
+
+    #[serde(rename = "Pool")]
+    pub(crate) pool: String,
+
+    #[serde(rename = "Quantity")]
+    pub(crate) quantity: Decimal,
+
+    #[serde(rename = "Cost Basis")]
+    pub(crate) basis: Decimal,
+
+    #[serde(rename = "Market Value")]
+    pub(crate) market_value: Decimal,
+
+    #[serde(rename = "Loss")]
+    pub(crate) loss: Decimal,
+
+    // 70 % of the loss, see `TaxEstimate`
+    #[serde(rename = "Deductible Loss")]
+    pub(crate) deductible_loss: Decimal,
+
+    #[serde(rename = "Capital Income")]
+    pub(crate) capital_income: Decimal,
+
+    #[serde(rename = "Capital Income After Sale")]
+    pub(crate) capital_income_after: Decimal,
+
+    #[serde(rename = "Tax")]
+    pub(crate) tax: Decimal,
+
+    #[serde(rename = "Tax After Sale")]
+    pub(crate) tax_after: Decimal,
+}
+
+impl Harvest {
+    /// Returns the loss that selling `holding` would realise, when it is valued below its cost
+    /// basis. `net_incomes` are the net incomes of the taxable trades of the year so far, in whole
+    /// kronor.
+    pub(crate) fn try_new(holding: &Holding, net_incomes: &[Decimal], other_capital_income: Decimal) -> Option<Self> {
+        let basis = holding.basis?;
+        let market_value = holding.market_value?;
+        let loss = market_value - basis;
+        if loss >= dec!(0) {
+            return None;
+        }
+
+        let before = TaxEstimate::new(net_incomes, other_capital_income);
+        let after = TaxEstimate::new(&[net_incomes, &[round_kronor(loss)]].concat(), other_capital_income);
+
+        Some(Harvest {
+            currency: holding.currency.clone(),
+            pool: holding.pool.clone(),
+            quantity: holding.quantity,
+            basis,
+            market_value,
+            loss,
+            deductible_loss: after.deductible_losses - before.deductible_losses,
+            capital_income: before.capital_income,
+            capital_income_after: after.capital_income,
+            tax: before.tax,
+            tax_after: after.tax,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::calculator::Holding;
+    use crate::skatteverket::Harvest;
+    use rust_decimal_macros::dec;
+    use std::error::Error;
+
+    #[test]
+    fn should_suggest_harvest() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let holding = |basis, market_value| Holding {
+            currency: "EOS".to_string(),
+            pool: "Current".to_string(),
+            quantity: dec!(100),
+            average_cost: None,
+            basis: Some(basis),
+            price: None,
+            market_value: Some(market_value),
+            unrealized: None,
+        };
+        let net_incomes = vec![dec!(20000), dec!(-1000)];
+
+        /*
+         * When
+         */
+        let harvest = Harvest::try_new(&holding(dec!(15000), dec!(5000)), &net_incomes, dec!(0));
+
+        /*
+         * Then
+         */
+        let harvest = harvest.unwrap();
+        assert_eq!(harvest.loss, dec!(-10000));
+        assert_eq!(harvest.deductible_loss, dec!(7000));
+        assert_eq!(harvest.capital_income, dec!(19300));
+        assert_eq!(harvest.capital_income_after, dec!(12300));
+        assert_eq!(harvest.tax, dec!(5790));
+        assert_eq!(harvest.tax_after, dec!(3690));
+        assert_eq!(Harvest::try_new(&holding(dec!(5000), dec!(15000)), &net_incomes, dec!(0)), None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+/*
+ * https://www.skatteverket.se/download/18.6e8a1495181dad540843eb2/1665748259651/SKV269_28_(2022P4).pdf
+ */
+
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::SruFileConfig;
+use super::encoding;
+
+/// The INFO.SRU file that describes the sender of the BLANKETTER.SRU file next to it.
+#[derive(Debug, Serialize)]
+pub(crate) struct InfoSru {
+    // #FILNAMN
+    // <Filnamn> Namnet på den fil som innehåller blanketterna.
+    file_name: String,
+
+    // #ORGNR
+    // <OrgNr> Person-/organisationsnummer för uppgiftslämnaren.
+    // Anges i formen SSÅÅMMDDNNNK.
+    org_num: String,
+
+    // #NAMN
+    name: String,
+
+    // #ADRESS
+    address: Option<String>,
+
+    // #POSTNR
+    // <Postnr> Postnummer, fem siffror utan mellanslag.
+    postcode: String,
+
+    // #POSTORT
+    city: String,
+
+    // #EMAIL
+    email: Option<String>,
+}
+
+impl InfoSru {
+    pub(crate) const FILE_NAME: &'static str = "INFO.SRU";
+
+    /// Returns the INFO.SRU for the sender in `config`, for the blankett file `file_name`.
+    /// The name, postcode and city of the sender are mandatory.
+    pub(crate) fn try_new(config: &SruFileConfig, file_name: &str) -> Result<Self> {
+        let mandatory = |value: &Option<String>, flag: &str| {
+            value.clone().ok_or(anyhow!("{} is mandatory to write {}", flag, Self::FILE_NAME))
+        };
+
+        Ok(InfoSru {
+            file_name: file_name.to_string(),
+            org_num: config.sru_org_num.clone(),
+            name: mandatory(&config.sru_org_name, "--sru-org-name <SRU_ORG_NAME>")?,
+            address: config.sru_org_address.clone(),
+            postcode: mandatory(&config.sru_org_postcode, "--sru-org-postcode <SRU_ORG_POSTCODE>")?.replace(' ', ""),
+            city: mandatory(&config.sru_org_city, "--sru-org-city <SRU_ORG_CITY>")?,
+            email: config.sru_org_email.clone(),
+        })
+    }
+
+    /// Writes the file in ISO-8859-1, with `\r\n` line endings when `crlf` is set.
+    pub(crate) fn write(&self, handle: impl Write, crlf: bool) -> Result<()> {
+        let mut buf = vec![];
+
+        // #DATABESKRIVNING_START Markerar att databeskrivningen börjar.
+        writeln!(buf, "#DATABESKRIVNING_START")?;
+        writeln!(buf, "#PRODUKT SRU")?;
+        writeln!(buf, "#FILNAMN {}", self.file_name)?;
+        writeln!(buf, "#DATABESKRIVNING_SLUT")?;
+
+        // #MEDIELEV_START Markerar att uppgifterna om uppgiftslämnaren börjar.
+        writeln!(buf, "#MEDIELEV_START")?;
+        writeln!(buf, "#ORGNR {}", self.org_num)?;
+        writeln!(buf, "#NAMN {}", self.name)?;
+        if let Some(address) = &self.address {
+            writeln!(buf, "#ADRESS {}", address)?;
+        }
+        writeln!(buf, "#POSTNR {}", self.postcode)?;
+        writeln!(buf, "#POSTORT {}", self.city)?;
+        if let Some(email) = &self.email {
+            writeln!(buf, "#EMAIL {}", email)?;
+        }
+        writeln!(buf, "#MEDIELEV_SLUT")?;
+
+        encoding::write_latin1(&String::from_utf8(buf)?, crlf, handle)
+            .with_context(|| format!("Could not write {}", Self::FILE_NAME))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::skatteverket::InfoSru;
+    use crate::SruFileConfig;
+
+    #[test]
+    fn should_write_info_sru() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let config = SruFileConfig {
+            sru_org_num: "195012310123".to_string(),
+            sru_org_name: Some("Svea Specimen".to_string()),
+            sru_org_address: Some("Storgatan 1".to_string()),
+            sru_org_postcode: Some("111 22".to_string()),
+            sru_org_city: Some("Stockholm".to_string()),
+            sru_org_email: None,
+            sru_dir: None,
+            sru_crlf: false,
+            sru_ink1: false,
+            sru_timestamp: None,
+            taxpayers: vec![],
+        };
+
+        /*
+         * When
+         */
+        let info_sru = InfoSru::try_new(&config, "BLANKETTER.SRU")?;
+        let mut buf = vec![];
+        info_sru.write(&mut buf, false)?;
+
+        /*
+         * Then
+         */
+        let output = String::from_utf8(buf)?;
+        assert_eq!(output, "\
+            #DATABESKRIVNING_START\n\
+            #PRODUKT SRU\n\
+            #FILNAMN BLANKETTER.SRU\n\
+            #DATABESKRIVNING_SLUT\n\
+            #MEDIELEV_START\n\
+            #ORGNR 195012310123\n\
+            #NAMN Svea Specimen\n\
+            #ADRESS Storgatan 1\n\
+            #POSTNR 11122\n\
+            #POSTORT Stockholm\n\
+            #MEDIELEV_SLUT\n\
+        ");
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_require_the_city_of_the_sender() {
+        /*
+         * Given
+         */
+        let config = SruFileConfig {
+            sru_org_num: "195012310123".to_string(),
+            sru_org_name: Some("Svea Specimen".to_string()),
+            sru_org_address: Some("Storgatan 1".to_string()),
+            sru_org_postcode: Some("111 22".to_string()),
+            sru_org_city: None,
+            sru_org_email: None,
+            sru_dir: None,
+            sru_crlf: false,
+            sru_ink1: false,
+            sru_timestamp: None,
+            taxpayers: vec![],
+        };
+
+        /*
+         * When
+         */
+        let info_sru = InfoSru::try_new(&config, "BLANKETTER.SRU");
+
+        /*
+         * Then
+         */
+        assert_eq!(info_sru.unwrap_err().to_string(), "--sru-org-city <SRU_ORG_CITY> is mandatory to write INFO.SRU");
+    }
+
+    #[test]
+    fn should_write_info_sru_in_latin1() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let config = SruFileConfig {
+            sru_org_num: "195012310123".to_string(),
+            sru_org_name: Some("Åsa Öström".to_string()),
+            sru_org_address: None,
+            sru_org_postcode: Some("413 01".to_string()),
+            sru_org_city: Some("Göteborg".to_string()),
+            sru_org_email: None,
+            sru_dir: None,
+            sru_crlf: false,
+            sru_ink1: false,
+            sru_timestamp: None,
+            taxpayers: vec![],
+        };
+
+        /*
+         * When
+         */
+        let info_sru = InfoSru::try_new(&config, "BLANKETTER.SRU")?;
+        let mut buf = vec![];
+        info_sru.write(&mut buf, false)?;
+
+        /*
+         * Then
+         */
+        let expected: &[u8] = b"#ORGNR 195012310123\n#NAMN \xC5sa \xD6str\xF6m\n#POSTNR 41301\n#POSTORT G\xF6teborg\n#MEDIELEV_SLUT\n";
+        assert!(buf.ends_with(expected));
+
+        Ok(())
+    }
+}
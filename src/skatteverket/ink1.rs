@@ -0,0 +1,303 @@
+/*
+ * https://www.skatteverket.se/privat/skatter/vardepapper/andratillgangar/kryptovalutor.4.15532c7b1442f256bae11b60.html
+ */
+
+use anyhow::{anyhow, Context, Result};
+use csv::ReaderBuilder;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::sru_file::{Form, Identity, Information, InformationGroup};
+use super::tax_estimate;
+use super::{K4Spec, SruFile};
+
+/// The blankett versions and field codes of the INK1 boxes that the sums of K4 are carried over
+/// to, one row per income year.
+const INK1_SPEC: &str = include_str!("ink1.csv");
+
+/// The INK1 blankett of one income year.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub(crate) struct Ink1Spec {
+    #[serde(rename = "Income Year")]
+    income_year: u16,
+
+    // The blankett block, e.g. `INK1-2023P1`
+    #[serde(rename = "Blankett")]
+    pub(crate) blankett: String,
+
+    // 7.4 Vinst på andra tillgångar än marknadsnoterade aktier m.m., K4 avsnitt C och D
+    #[serde(rename = "Gains")]
+    pub(crate) gains: String,
+
+    // 8.3 Förlust på andra tillgångar än marknadsnoterade aktier m.m., 70 % av förlusten
+    #[serde(rename = "Deductible Losses")]
+    pub(crate) deductible_losses: String,
+}
+
+impl Ink1Spec {
+    /// Returns the INK1 blankett for the income year `income_year`.
+    pub(crate) fn for_year(income_year: u16) -> Result<Self> {
+        ReaderBuilder::new()
+            .has_headers(true)
+            .from_reader(INK1_SPEC.as_bytes())
+            .deserialize::<Ink1Spec>()
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .context("Invalid INK1 spec")?
+            .into_iter()
+            .find(|spec| spec.income_year == income_year)
+            .ok_or(anyhow!("No INK1 blankett for the income year {}", income_year))
+    }
+}
+
+/// The figures of one taxpayer that are carried over from the sums of their K4 forms to INK1.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Ink1 {
+    pub(crate) org_num: String,
+    pub(crate) name: Option<String>,
+
+    // K4 D Summa vinst
+    pub(crate) gains: Decimal,
+
+    // K4 D Summa förlust
+    pub(crate) losses: Decimal,
+}
+
+/// A box on INK1 and the amount to enter in it.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct Ink1Box {
+    #[serde(rename = "Taxpayer")]
+    pub(crate) taxpayer: String,
+
+    #[serde(rename = "Box")]
+    pub(crate) ink1_box: &'static str,
+
+    #[serde(rename = "Description")]
+    pub(crate) description: String,
+
+    #[serde(rename = "Amount")]
+    pub(crate) amount: Decimal,
+}
+
+impl Ink1 {
+    /// Adds up the K4 forms in `sru_file` per taxpayer, in the order the taxpayers first appear.
+    pub(crate) fn of(sru_file: &SruFile, spec: &K4Spec) -> Result<Vec<Ink1>> {
+        let mut ink1s: Vec<Ink1> = vec![];
+
+        for form in sru_file.forms.iter().filter(|form| form.is_k4()) {
+            let sums = form.sums(spec)?;
+            match ink1s.iter_mut().find(|ink1| ink1.org_num == form.identity.org_num) {
+                Some(ink1) => {
+                    ink1.gains += sums.gain;
+                    ink1.losses += sums.loss;
+                }
+                None => ink1s.push(Ink1 {
+                    org_num: form.identity.org_num.clone(),
+                    name: form.name.clone(),
+                    gains: sums.gain,
+                    losses: sums.loss,
+                }),
+            }
+        }
+
+        Ok(ink1s)
+    }
+
+    /// 70 % of the losses on K4, in whole kronor.
+    pub(crate) fn deductible_losses(&self) -> Decimal {
+        tax_estimate::deductible_losses(self.losses)
+    }
+
+    /// What to enter where on INK1 in the e-service.
+    pub(crate) fn boxes(&self) -> [Ink1Box; 2] {
+        [
+            Ink1Box {
+                taxpayer: self.org_num.clone(),
+                ink1_box: "7.4",
+                description: "Vinst på K4 avsnitt C och D".to_string(),
+                amount: self.gains,
+            },
+            Ink1Box {
+                taxpayer: self.org_num.clone(),
+                ink1_box: "8.3",
+                description: format!("Förlust på K4 avsnitt C och D, 70 % av {}", self.losses),
+                amount: self.deductible_losses(),
+            },
+        ]
+    }
+
+    /// The INK1 blankett block with the figures. Boxes that are 0 are left out.
+    pub(crate) fn to_form(&self, spec: &Ink1Spec) -> Form {
+        let information = [
+            (&spec.gains, self.gains),
+            (&spec.deductible_losses, self.deductible_losses()),
+        ]
+            .into_iter()
+            .filter(|(_, value)| !value.is_zero())
+            .map(|(field_code, value)| Information { field_code: field_code.clone(), field_value: value.to_string() })
+            .collect();
+
+        Form {
+            form: spec.blankett.clone(),
+            identity: Identity { org_num: self.org_num.clone(), created: chrono::Utc::now().naive_utc() },
+            name: self.name.clone(),
+            information_groups: vec![InformationGroup { trade: spec.blankett.clone(), information }],
+            totals: vec![],
+            system_info: None,
+        }
+    }
+}
+
+impl SruFile {
+    /// Adds an INK1 blankett block for every taxpayer after the K4 forms.
+    pub(crate) fn with_ink1(mut self, spec: &K4Spec, ink1_spec: &Ink1Spec) -> Result<Self> {
+        let ink1_forms: Vec<Form> = Ink1::of(&self, spec)?.iter().map(|ink1| ink1.to_form(ink1_spec)).collect();
+        self.forms.extend(ink1_forms);
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::skatteverket::{Ink1, Ink1Spec, K4Spec, SruFile};
+    use rust_decimal_macros::dec;
+
+    // Two K4 forms of the first taxpayer and one of the second
+    const SRU_FILE: &str = "\
+        #BLANKETT K4-2023P4\n\
+        #IDENTITET 195012310123 20240428 222030\n\
+        #UPPGIFT 3410 55\n\
+        #UPPGIFT 3411 EOS\n\
+        #UPPGIFT 3412 891\n\
+        #UPPGIFT 3413 335\n\
+        #UPPGIFT 3414 556\n\
+        #BLANKETTSLUT\n\
+        #BLANKETT K4-2023P4\n\
+        #IDENTITET 195501014327 20240428 222030\n\
+        #UPPGIFT 3410 1\n\
+        #UPPGIFT 3411 ETH\n\
+        #UPPGIFT 3412 20\n\
+        #UPPGIFT 3413 10\n\
+        #UPPGIFT 3414 10\n\
+        #BLANKETTSLUT\n\
+        #BLANKETT K4-2023P4\n\
+        #IDENTITET 195012310123 20240428 222030\n\
+        #UPPGIFT 3410 2\n\
+        #UPPGIFT 3411 BTC\n\
+        #UPPGIFT 3412 100\n\
+        #UPPGIFT 3413 151\n\
+        #UPPGIFT 3415 51\n\
+        #BLANKETTSLUT\n\
+        #FIL_SLUT\n\
+    ";
+
+    #[test]
+    fn should_add_up_k4_forms_per_taxpayer() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let spec = K4Spec::for_year(2023)?;
+        let sru_file = SruFile::parse(SRU_FILE)?;
+
+        /*
+         * When
+         */
+        let ink1s = Ink1::of(&sru_file, &spec)?;
+
+        /*
+         * Then
+         */
+        let sums: Vec<_> = ink1s.iter().map(|ink1| (ink1.org_num.as_str(), ink1.gains, ink1.losses)).collect();
+        assert_eq!(sums, vec![
+            ("195012310123", dec!(556), dec!(51)),
+            ("195501014327", dec!(10), dec!(0)),
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_deduct_70_percent_of_losses_in_whole_kronor() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let spec = K4Spec::for_year(2023)?;
+        let sru_file = SruFile::parse(SRU_FILE)?;
+
+        /*
+         * When
+         */
+        let ink1s = Ink1::of(&sru_file, &spec)?;
+
+        /*
+         * Then
+         */
+        let boxes: Vec<_> = ink1s.iter().flat_map(Ink1::boxes).map(|b| (b.taxpayer, b.ink1_box, b.amount)).collect();
+        // 70 % of 51 is 35.7
+        assert_eq!(boxes, vec![
+            ("195012310123".to_string(), "7.4", dec!(556)),
+            ("195012310123".to_string(), "8.3", dec!(36)),
+            ("195501014327".to_string(), "7.4", dec!(10)),
+            ("195501014327".to_string(), "8.3", dec!(0)),
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_write_ink1_after_k4_without_boxes_of_0() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let spec = K4Spec::for_year(2023)?;
+        let sru_file = SruFile::parse(SRU_FILE)?;
+
+        /*
+         * When
+         */
+        let sru_file = sru_file.with_ink1(&spec, &Ink1Spec::for_year(2023)?)?.created_at("2024-04-28T22:20:30".parse()?);
+        let mut buf = vec![];
+        sru_file.write(&mut buf, false)?;
+
+        /*
+         * Then
+         */
+        let output = String::from_utf8(buf)?;
+        assert!(output.ends_with("\
+            #UPPGIFT 3415 51\n\
+            #BLANKETTSLUT\n\
+            #BLANKETT INK1-2023P1\n\
+            #IDENTITET 195012310123 20240428 222030\n\
+            #UPPGIFT 1103 556\n\
+            #UPPGIFT 1172 36\n\
+            #BLANKETTSLUT\n\
+            #BLANKETT INK1-2023P1\n\
+            #IDENTITET 195501014327 20240428 222030\n\
+            #UPPGIFT 1103 10\n\
+            #BLANKETTSLUT\n\
+            #FIL_SLUT\n\
+        "), "{}", output);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_leave_ink1_out_of_k4_validation() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let spec = K4Spec::for_year(2023)?;
+        let sru_file = SruFile::parse(SRU_FILE)?;
+
+        /*
+         * When
+         */
+        let sru_file = sru_file.with_ink1(&spec, &Ink1Spec::for_year(2023)?)?;
+
+        /*
+         * Then
+         */
+        assert!(sru_file.validate(&spec).is_ok());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+use anyhow::{anyhow, Context, Result};
+use csv::ReaderBuilder;
+use serde::Deserialize;
+
+/// The blankett versions and field codes of section D of K4, one row per line and income year.
+/// A new version of the spec from Skatteverket is added as rows to this file.
+const K4_SPEC: &str = include_str!("k4.csv");
+
+/// The field codes of the sums of section D of K4, one row per income year.
+const K4_TOTALS_SPEC: &str = include_str!("k4_totals.csv");
+
+/// The field codes of one line in section D of K4.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub(crate) struct K4Line {
+    #[serde(rename = "Income Year")]
+    income_year: u16,
+
+    #[serde(rename = "Blankett")]
+    blankett: String,
+
+    #[serde(rename = "Line")]
+    pub(crate) line: usize,
+
+    // D.1 Antal/Belopp i utländsk valuta
+    #[serde(rename = "Quantity")]
+    pub(crate) quantity: String,
+
+    // D.1 Beteckning/Valutakod
+    #[serde(rename = "Designation")]
+    pub(crate) designation: String,
+
+    // D.1 Försäljningspris/Återbetalat belopp omräknat till svenska kronor
+    #[serde(rename = "Sales Price")]
+    pub(crate) sales_price: String,
+
+    // D.1 Omkostnadsbelopp/Utlånat belopp omräknat till svenska kronor
+    #[serde(rename = "Costs")]
+    pub(crate) costs: String,
+
+    // D.1 Vinst
+    #[serde(rename = "Gain")]
+    pub(crate) gain: String,
+
+    // D.1 Förlust
+    #[serde(rename = "Loss")]
+    pub(crate) loss: String,
+}
+
+impl K4Line {
+    pub(crate) fn field_codes(&self) -> [&str; 6] {
+        [&self.quantity, &self.designation, &self.sales_price, &self.costs, &self.gain, &self.loss]
+    }
+}
+
+/// The field codes of the sums of section D of K4, which are carried over to INK1.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub(crate) struct K4Totals {
+    #[serde(rename = "Blankett")]
+    blankett: String,
+
+    // D Summa försäljningspris
+    #[serde(rename = "Sales Price")]
+    pub(crate) sales_price: String,
+
+    // D Summa omkostnadsbelopp
+    #[serde(rename = "Costs")]
+    pub(crate) costs: String,
+
+    // D Summa vinst
+    #[serde(rename = "Gain")]
+    pub(crate) gain: String,
+
+    // D Summa förlust
+    #[serde(rename = "Loss")]
+    pub(crate) loss: String,
+}
+
+impl K4Totals {
+    pub(crate) fn field_codes(&self) -> [&str; 4] {
+        [&self.sales_price, &self.costs, &self.gain, &self.loss]
+    }
+}
+
+/// The K4 blankett of one income year.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct K4Spec {
+    pub(crate) income_year: u16,
+
+    // The blankett block, e.g. `K4-2022P4`
+    pub(crate) blankett: String,
+
+    pub(crate) lines: Vec<K4Line>,
+
+    pub(crate) totals: K4Totals,
+}
+
+impl K4Spec {
+    /// Returns the K4 blankett for the income year `income_year`.
+    pub(crate) fn for_year(income_year: u16) -> Result<Self> {
+        let rows = Self::read_rows()?;
+
+        let lines: Vec<K4Line> = rows.iter().filter(|row| row.income_year == income_year).cloned().collect();
+        let blankett = lines.first().map(|line| line.blankett.clone())
+            .ok_or_else(|| {
+                let mut years: Vec<u16> = rows.iter().map(|row| row.income_year).collect();
+                years.dedup();
+                anyhow!("No K4 blankett for the income year {}, supported years are {:?}", income_year, years)
+            })?;
+
+        let totals = Self::read_totals(&blankett)?;
+        Ok(K4Spec { income_year, blankett, lines, totals })
+    }
+
+    /// Returns the K4 blankett with the block code `blankett`, e.g. `K4-2022P4`.
+    pub(crate) fn for_blankett(blankett: &str) -> Result<Self> {
+        let lines: Vec<K4Line> = Self::read_rows()?.into_iter().filter(|row| row.blankett == blankett).collect();
+        let income_year = lines.first().map(|line| line.income_year)
+            .ok_or(anyhow!("Unknown K4 blankett {}", blankett))?;
+
+        let totals = Self::read_totals(blankett)?;
+        Ok(K4Spec { income_year, blankett: blankett.to_string(), lines, totals })
+    }
+
+    /// Returns the line of section D with the field `field_code`.
+    pub(crate) fn line_of(&self, field_code: &str) -> Option<&K4Line> {
+        self.lines.iter().find(|line| line.field_codes().contains(&field_code))
+    }
+
+    fn read_rows() -> Result<Vec<K4Line>> {
+        ReaderBuilder::new()
+            .has_headers(true)
+            .from_reader(K4_SPEC.as_bytes())
+            .deserialize::<K4Line>()
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .context("Invalid K4 spec")
+    }
+
+    fn read_totals(blankett: &str) -> Result<K4Totals> {
+        ReaderBuilder::new()
+            .has_headers(true)
+            .from_reader(K4_TOTALS_SPEC.as_bytes())
+            .deserialize::<K4Totals>()
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .context("Invalid K4 totals spec")?
+            .into_iter()
+            .find(|totals| totals.blankett == blankett)
+            .ok_or(anyhow!("No totals for the K4 blankett {}", blankett))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::skatteverket::K4Spec;
+
+    #[test]
+    fn should_read_k4_spec() -> anyhow::Result<()> {
+        /*
+         * When
+         */
+        let spec = K4Spec::for_year(2022)?;
+
+        /*
+         * Then
+         */
+        assert_eq!(spec.blankett, "K4-2022P4");
+        assert_eq!(spec.lines.len(), 7);
+        assert_eq!(spec.lines[0].quantity, "3410");
+        assert_eq!(spec.lines[6].loss, "3475");
+        assert_eq!(spec.totals.field_codes(), ["3500", "3501", "3503", "3504"]);
+        assert!(K4Spec::for_year(1999).is_err());
+        assert_eq!(K4Spec::for_blankett("K4-2022P4")?, spec);
+        assert_eq!(spec.line_of("3424").map(|line| line.line), Some(2));
+
+        Ok(())
+    }
+}
@@ -1,2 +1,24 @@
+mod encoding;
+mod harvest;
+mod ink1;
+mod info_sru;
+mod k4_spec;
+mod rounding;
+mod sru_diff;
 mod sru_file;
-pub(crate) use sru_file::SruFile;
\ No newline at end of file
+mod sru_reader;
+mod sru_totals;
+mod system_info;
+mod tax_estimate;
+mod taxpayer;
+mod validator;
+pub(crate) use harvest::Harvest;
+pub(crate) use info_sru::InfoSru;
+pub(crate) use ink1::{Ink1, Ink1Box, Ink1Spec};
+pub(crate) use k4_spec::{K4Line, K4Spec, K4Totals};
+pub(crate) use rounding::{round_kronor, Rounding};
+pub(crate) use sru_diff::SruDiff;
+pub(crate) use sru_file::SruFile;
+pub(crate) use system_info::system_info;
+pub(crate) use tax_estimate::TaxEstimate;
+pub use taxpayer::Taxpayer;
@@ -0,0 +1,127 @@
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::Serialize;
+
+use crate::calculator::TaxableTrade;
+
+/// Rounds `amount` to whole kronor, halves away from zero.
+pub(crate) fn round_kronor(amount: Decimal) -> Decimal {
+    amount.round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero).normalize()
+}
+
+/// A taxable trade in whole kronor. The income and the cost are rounded, and the net income is
+/// the difference of the rounded figures, so that every line adds up. The difference to the
+/// exact net income is the rounding difference.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub(crate) struct Rounding {
+    #[serde(rename = "Trade")]
+    pub(crate) trade: String,
+
+    #[serde(rename = "Income")]
+    pub(crate) income: Decimal,
+
+    #[serde(rename = "Cost")]
+    pub(crate) costs: Decimal,
+
+    #[serde(rename = "Net Income")]
+    pub(crate) net_income: Decimal,
+
+    #[serde(rename = "Rounded Income")]
+    pub(crate) rounded_income: Decimal,
+
+    #[serde(rename = "Rounded Cost")]
+    pub(crate) rounded_costs: Decimal,
+
+    #[serde(rename = "Rounded Net Income")]
+    pub(crate) rounded_net_income: Decimal,
+
+    #[serde(rename = "Difference")]
+    pub(crate) difference: Decimal,
+}
+
+impl Rounding {
+    /// Returns `None` when some of the cost is not valued in cash.
+    pub(crate) fn try_new(taxable_trade: &TaxableTrade) -> Option<Self> {
+        let income = taxable_trade.income.amount();
+        let costs = taxable_trade.sum_cash_amount()?;
+        let net_income = taxable_trade.net_income?;
+
+        let rounded_income = round_kronor(income);
+        let rounded_costs = round_kronor(costs);
+        let rounded_net_income = rounded_income + rounded_costs;
+
+        Some(Rounding {
+            trade: taxable_trade.to_string(),
+            income,
+            costs,
+            net_income,
+            rounded_income,
+            rounded_costs,
+            rounded_net_income,
+            difference: rounded_net_income - net_income,
+        })
+    }
+
+    /// Adds up the rows into a row named `Total`.
+    pub(crate) fn total(rows: &[Rounding]) -> Rounding {
+        rows.iter()
+            .fold(Rounding { trade: "Total".to_string(), ..Default::default() }, |acc, r|
+                Rounding {
+                    income: acc.income + r.income,
+                    costs: acc.costs + r.costs,
+                    net_income: acc.net_income + r.net_income,
+                    rounded_income: acc.rounded_income + r.rounded_income,
+                    rounded_costs: acc.rounded_costs + r.rounded_costs,
+                    rounded_net_income: acc.rounded_net_income + r.rounded_net_income,
+                    difference: acc.difference + r.difference,
+                    ..acc
+                }
+            )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::calculator::{Money, TaxableTrade};
+    use crate::skatteverket::Rounding;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn should_round_net_income_from_rounded_figures() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let taxable_trade = |date: &str, income: Decimal, costs: Decimal| TaxableTrade::new(
+            Some(date.to_string()),
+            "EOS".to_string(),
+            dec!(-25),
+            Money::new_cash("SEK".to_string(), income),
+            vec![Money::new_cash("SEK".to_string(), costs)],
+            Some(income + costs),
+        );
+
+        /*
+         * When
+         */
+        let rows: Vec<Rounding> = [
+            taxable_trade("2023-01-02 10:00:00", dec!(394.86), dec!(-182.74)),
+            taxable_trade("2023-05-06 10:00:00", dec!(495.50), dec!(-152.29)),
+        ]
+            .iter()
+            .filter_map(Rounding::try_new)
+            .collect();
+        let total = Rounding::total(&rows);
+
+        /*
+         * Then
+         */
+        assert_eq!((rows[0].rounded_income, rows[0].rounded_costs, rows[0].rounded_net_income), (dec!(395), dec!(-183), dec!(212)));
+        assert_eq!((rows[1].rounded_income, rows[1].rounded_costs, rows[1].rounded_net_income), (dec!(496), dec!(-152), dec!(344)));
+        assert_eq!(rows[1].difference, dec!(0.79));
+        assert_eq!(total.net_income, dec!(555.33));
+        assert_eq!(total.rounded_net_income, dec!(556));
+        assert_eq!(total.difference, dec!(0.67));
+
+        Ok(())
+    }
+}
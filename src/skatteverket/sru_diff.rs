@@ -0,0 +1,216 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use super::sru_file::Form;
+use super::SruFile;
+
+/// A field that differs between an SRU file and the forms computed from the statement.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct SruDiff {
+    #[serde(rename = "Form")]
+    pub(crate) form: usize,
+
+    #[serde(rename = "Field")]
+    pub(crate) field: String,
+
+    #[serde(rename = "In File")]
+    pub(crate) in_file: Option<String>,
+
+    #[serde(rename = "Computed")]
+    pub(crate) computed: Option<String>,
+
+    // The taxable trade of the computed field
+    #[serde(rename = "Trade")]
+    pub(crate) trade: Option<String>,
+}
+
+impl SruDiff {
+    /// Compares `file` with `computed` form by form and field by field.
+    pub(crate) fn diff(file: &SruFile, computed: &SruFile) -> Vec<SruDiff> {
+        let forms = file.forms.len().max(computed.forms.len());
+
+        (0..forms)
+            .flat_map(|i| {
+                let in_file = file.forms.get(i).map(fields).unwrap_or_default();
+                let computed = computed.forms.get(i).map(fields).unwrap_or_default();
+
+                let mut codes: Vec<&String> = in_file.keys().chain(computed.keys()).collect();
+                codes.sort();
+                codes.dedup();
+
+                codes.into_iter()
+                    .filter_map(|code| {
+                        let in_file = in_file.get(code).map(|(value, _)| value.clone());
+                        let (computed, trade) = computed.get(code).cloned().unzip();
+                        (in_file != computed).then(|| SruDiff {
+                            form: i + 1,
+                            field: code.clone(),
+                            in_file,
+                            computed,
+                            trade: trade.flatten(),
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+/// The values of the form by field code, with the taxable trade behind each field.
+fn fields(form: &Form) -> BTreeMap<String, (String, Option<String>)> {
+    let mut fields = BTreeMap::new();
+    fields.insert("#BLANKETT".to_string(), (form.form.clone(), None));
+    fields.insert("#IDENTITET".to_string(), (form.identity.org_num.clone(), None));
+    if let Some(name) = &form.name {
+        fields.insert("#NAMN".to_string(), (name.clone(), None));
+    }
+    for group in &form.information_groups {
+        for info in &group.information {
+            fields.insert(info.field_code.clone(), (info.field_value.clone(), Some(group.trade.clone())));
+        }
+    }
+    for info in &form.totals {
+        fields.insert(info.field_code.clone(), (info.field_value.clone(), None));
+    }
+    fields
+}
+
+#[cfg(test)]
+mod test {
+    use crate::skatteverket::{SruDiff, SruFile};
+
+    const IN_FILE: &str = "\
+        #BLANKETT K4-2022P4\n\
+        #IDENTITET 195012310123 20230428 222030\n\
+        #NAMN Svea Specimen\n\
+        #UPPGIFT 3410 55\n\
+        #UPPGIFT 3411 EOS\n\
+        #UPPGIFT 3412 891\n\
+        #UPPGIFT 3413 335\n\
+        #UPPGIFT 3414 556\n\
+        #BLANKETTSLUT\n\
+        #FIL_SLUT\n\
+    ";
+
+    // Form, field, in file, computed
+    type Diff = (usize, String, Option<String>, Option<String>);
+
+    /// Diffs `IN_FILE` with `computed`.
+    fn diff_of(computed: &str) -> anyhow::Result<Vec<Diff>> {
+        let diffs = SruDiff::diff(&SruFile::parse(IN_FILE)?, &SruFile::parse(computed)?);
+        Ok(diffs.into_iter().map(|d| (d.form, d.field, d.in_file, d.computed)).collect())
+    }
+
+    #[test]
+    fn should_not_diff_equal_forms_made_at_other_times() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let computed = IN_FILE.replace("20230428 222030", "20240101 120000");
+
+        /*
+         * When
+         */
+        let diffs = diff_of(&computed)?;
+
+        /*
+         * Then
+         */
+        assert_eq!(diffs, vec![]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_diff_changed_field() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let computed = IN_FILE.replace("#UPPGIFT 3413 335\n#UPPGIFT 3414 556\n", "#UPPGIFT 3413 335\n#UPPGIFT 3414 555\n");
+
+        /*
+         * When
+         */
+        let diffs = diff_of(&computed)?;
+
+        /*
+         * Then
+         */
+        assert_eq!(diffs, vec![(1, "3414".to_string(), Some("556".to_string()), Some("555".to_string()))]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_diff_field_in_only_one_of_the_files() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let computed = IN_FILE.replace("#UPPGIFT 3414 556\n", "#UPPGIFT 3415 9\n");
+
+        /*
+         * When
+         */
+        let diffs = diff_of(&computed)?;
+
+        /*
+         * Then
+         */
+        assert_eq!(diffs, vec![
+            (1, "3414".to_string(), Some("556".to_string()), None),
+            (1, "3415".to_string(), None, Some("9".to_string())),
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_diff_taxpayer() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let computed = IN_FILE.replace("#NAMN Svea Specimen\n", "");
+
+        /*
+         * When
+         */
+        let diffs = diff_of(&computed)?;
+
+        /*
+         * Then
+         */
+        assert_eq!(diffs, vec![(1, "#NAMN".to_string(), Some("Svea Specimen".to_string()), None)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_diff_form_missing_from_computed() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let computed = "#FIL_SLUT\n";
+
+        /*
+         * When
+         */
+        let diffs = diff_of(computed)?;
+
+        /*
+         * Then
+         */
+        let fields: Vec<(usize, &str, bool)> = diffs.iter().map(|(form, field, _, computed)| (*form, field.as_str(), computed.is_none())).collect();
+        assert_eq!(fields, vec![
+            (1, "#BLANKETT", true),
+            (1, "#IDENTITET", true),
+            (1, "#NAMN", true),
+            (1, "3410", true),
+            (1, "3411", true),
+            (1, "3412", true),
+            (1, "3413", true),
+            (1, "3414", true),
+        ]);
+
+        Ok(())
+    }
+}
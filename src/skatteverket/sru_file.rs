@@ -2,26 +2,31 @@
  * https://www.skatteverket.se/download/18.6e8a1495181dad540843eb2/1665748259651/SKV269_28_(2022P4).pdf
  */
 
-use anyhow::Result;
-use chrono::Datelike;
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDateTime;
 use rust_decimal::Decimal;
 use serde::Serialize;
+use std::collections::HashMap;
 use std::io::Write;
 
 use crate::calculator::{Currency, TaxableTrade};
+use super::{encoding, round_kronor, K4Line, K4Spec, Rounding};
 
 #[derive(Debug, Serialize)]
 pub(crate) struct SruFile {
-    forms: Vec<Form>,
+    pub(crate) forms: Vec<Form>,
 }
 
 impl SruFile {
+    pub(crate) const FILE_NAME: &'static str = "BLANKETTER.SRU";
+
     pub(crate) fn try_new(
         taxable_trades: &Vec<TaxableTrade>,
+        spec: &K4Spec,
         org_num: String,
         name: Option<String>,
-    ) -> Option<Self> {
-        Form::try_from_taxable_trades(taxable_trades, org_num, name)
+    ) -> Result<Self> {
+        Form::try_from_taxable_trades(taxable_trades, spec, org_num, name)
             .map(|forms|
                 SruFile {
                     forms
@@ -29,15 +34,38 @@ impl SruFile {
             )
     }
 
-    pub(crate) fn write(&self, mut handle: impl Write) -> Result<()> {
-        for (i, form) in self.forms.iter().enumerate() {
-            form.write(i + 1, &mut handle)?;
+    /// Dates every form `created` instead of now, so that the file can be reproduced.
+    pub(crate) fn created_at(mut self, created: NaiveDateTime) -> Self {
+        self.forms.iter_mut().for_each(|form| form.identity.created = created);
+        self
+    }
+
+    /// Adds `system_info`, e.g. what produced the file, to every form.
+    pub(crate) fn with_system_info(mut self, system_info: String) -> Self {
+        self.forms.iter_mut().for_each(|form| form.system_info = Some(system_info.clone()));
+        self
+    }
+
+    /// Writes the file in ISO-8859-1, with `\r\n` line endings when `crlf` is set.
+    pub(crate) fn write(&self, handle: impl Write, crlf: bool) -> Result<()> {
+        // The K4 forms of every taxpayer are numbered from 1
+        let mut numbers: HashMap<&str, usize> = HashMap::new();
+
+        let mut buf = vec![];
+        for form in &self.forms {
+            let i = form.is_k4().then(|| {
+                let i = numbers.entry(&form.identity.org_num).or_default();
+                *i += 1;
+                *i
+            });
+            form.write(i, &mut buf)?;
         }
 
         // #FIL_SLUT Markerar att filen slutar.
-        writeln!(handle, "#FIL_SLUT")?;
+        writeln!(buf, "#FIL_SLUT")?;
 
-        Ok(())
+        encoding::write_latin1(&String::from_utf8(buf)?, crlf, handle)
+            .with_context(|| format!("Could not write {}", Self::FILE_NAME))
     }
 }
 
@@ -47,56 +75,63 @@ pub(crate) struct Form {
     // <BlankettTyp> Anger vilket blankettblock
     // som avses. Vid inlämning får värden enligt kolumnen ”Blankettblock” i
     // tabell 1, se ref[1]. Endast versaler, ”-” och siffror är tillåtna.
-    form: String,
+    pub(crate) form: String,
 
     // #IDENTITET
-    identity: Identity,
+    pub(crate) identity: Identity,
 
     // #NAMN
     // <Namn> Namnet på dig so lämnar uppgifterna.Om
     // uppgiftelämnas visas den sedan på mottagningskvittensen. Längden på
     // fältet får vara högst 250 tecken långt, dock används endast position
     // 1 - 25 på mottagningskvittensen.
-    name: Option<String>,
+    pub(crate) name: Option<String>,
 
     // #UPPGIFT
-    information_groups: Vec<InformationGroup>,
+    pub(crate) information_groups: Vec<InformationGroup>,
+
+    // #UPPGIFT of the sums of section D, see `SruTotals`
+    pub(crate) totals: Vec<Information>,
 
     // #SYSTEMINFO
     // Används till uppgiftslämnarens egna uppgifter. Endast en
     // post får lämnas. Skatteverket läser inte in posten.
-    system_info: Option<String>,
+    pub(crate) system_info: Option<String>,
 }
 
 impl Form {
-    pub(crate) fn write(&self, i: usize, mut handle: impl Write) -> Result<()> {
-        writeln!(handle, "#BLANKETT {}", self.form)?;
+    /// Whether the form is a K4 blankett, as opposed to e.g. an INK1 blankett.
+    pub(crate) fn is_k4(&self) -> bool {
+        self.form.starts_with("K4-")
+    }
 
-        let now = chrono::Utc::now();
+    /// Writes the form, numbered `i` in field 7014 if it is a K4 form.
+    pub(crate) fn write(&self, i: Option<usize>, mut handle: impl Write) -> Result<()> {
+        writeln!(handle, "#BLANKETT {}", self.form)?;
 
         let identity = &self.identity;
         writeln!(
             handle,
             "#IDENTITET {} {} {}",
             identity.org_num,
-
-            // <DatFramst> Datum för framställande av uppgifterna.
-            // Anges i formen SSÅÅMMDD.
-            now.format("%Y%m%d").to_string(),
-
-            // <TidFramst> Klockslag för framställande av uppgifterna.
-            // Anges i formen TTMMSS.
-            now.format("%H%M%S").to_string(),
+            identity.created.format("%Y%m%d"),
+            identity.created.format("%H%M%S"),
         )?;
 
         if let Some(name) = &self.name {
             writeln!(handle, "#NAMN {}", name)?;
         }
 
-        writeln!(handle, "#UPPGIFT 7014 {}", i)?;
+        if let Some(system_info) = &self.system_info {
+            writeln!(handle, "#SYSTEMINFO {}", system_info)?;
+        }
 
-        let information: Vec<&Information> = self.information_groups.iter().flatten().collect();
-        for info in information {
+        if let Some(i) = i {
+            writeln!(handle, "#UPPGIFT 7014 {}", i)?;
+        }
+
+        let information: Vec<&Information> = self.information_groups.iter().flat_map(|g| &g.information).collect();
+        for info in information.into_iter().chain(&self.totals) {
             writeln!(handle, "#UPPGIFT {} {}", info.field_code, info.field_value)?;
         }
 
@@ -108,42 +143,42 @@ impl Form {
 
     pub(crate) fn try_from_taxable_trades(
         taxable_trades: &Vec<TaxableTrade>,
+        spec: &K4Spec,
         org_num: String,
         name: Option<String>,
-    ) -> Option<Vec<Self>> {
-        let year = chrono::Utc::now().year() - 1;
-
+    ) -> Result<Vec<Self>> {
+        let created = chrono::Utc::now().naive_utc();
         let mut forms = vec![];
 
         let mut current_form = Form {
-            form: format!("K4-{}P4", year),
-            identity: Identity { org_num: org_num.clone() },
+            form: spec.blankett.clone(),
+            identity: Identity { org_num: org_num.clone(), created },
             name: name.clone(),
             information_groups: vec![],
+            totals: vec![],
             system_info: None,
         };
 
         for taxable_trade in taxable_trades {
             let currency = taxable_trade.currency.clone();
             let amount = taxable_trade.amount;
-            let income = taxable_trade.income.amount();
-            let costs = taxable_trade.sum_cash_amount()?;
-            let net_income = taxable_trade.net_income?;
+            let rounding = Rounding::try_new(taxable_trade).ok_or(anyhow!("All costs must be cash"))?;
 
-            if current_form.information_groups.len() < 7 {
-                let i = current_form.information_groups.len() + 1;
-                let info_vec = new_information_group(i, currency, amount, income, costs, net_income);
+            if current_form.information_groups.len() < spec.lines.len() {
+                let line = &spec.lines[current_form.information_groups.len()];
+                let info_vec = new_information_group(line, currency, amount, rounding);
                 current_form.information_groups.push(info_vec);
             } else {
                 forms.push(current_form);
 
                 current_form = Form {
-                    form: format!("K4-{}P4", year),
-                    identity: Identity { org_num: org_num.clone() },
+                    form: spec.blankett.clone(),
+                    identity: Identity { org_num: org_num.clone(), created },
                     name: name.clone(),
                     information_groups: vec![
-                        new_information_group(1, currency, amount, income, costs, net_income)
+                        new_information_group(&spec.lines[0], currency, amount, rounding)
                     ],
+                    totals: vec![],
                     system_info: None,
                 };
             }
@@ -151,20 +186,31 @@ impl Form {
 
         forms.push(current_form);
 
-        Some(forms)
+        for form in forms.iter_mut() {
+            form.totals = form.sums(spec)?.to_information(&spec.totals);
+        }
+
+        Ok(forms)
     }
 }
 
 #[derive(Debug, Serialize)]
-struct Identity {
+pub(crate) struct Identity {
     // <OrgNr> Person-/organisations-/samordningsnummer för
     // den som uppgifterna avser. Anges i formen
     // SSÅÅMMDDNNNK.
-    org_num: String,
+    pub(crate) org_num: String,
+
+    // <DatFramst> Datum för framställande av uppgifterna.
+    // Anges i formen SSÅÅMMDD.
+    // <TidFramst> Klockslag för framställande av uppgifterna.
+    // Anges i formen TTMMSS.
+    #[serde(skip)]
+    pub(crate) created: NaiveDateTime,
 }
 
 #[derive(Debug, Serialize)]
-struct Information {
+pub(crate) struct Information {
     // <FältKod> Den fältkod som finns angiven i fältnamns-
     // tabellen för respektive blankettblock. Med några få
     // undantag är det fältkoder som finns på respektive
@@ -172,46 +218,54 @@ struct Information {
     // fältnamnstabellen gäller att en fältkod får förekomma
     // endast en gång per blankettblock. #UPPGIFT får inte vara
     // blank utan ska innehålla en fältkod och värde.
-    field_code: String,
+    pub(crate) field_code: String,
 
     // <FältVärde> Det värde som ska redovisas för fältkoden.
-    field_value: String,
+    pub(crate) field_value: String,
 }
 
-type InformationGroup = Vec<Information>;
+/// The fields of one line in section D, and the taxable trade they report.
+#[derive(Debug, Serialize)]
+pub(crate) struct InformationGroup {
+    pub(crate) trade: String,
+    pub(crate) information: Vec<Information>,
+}
 
+/// The fields of one line, in whole kronor as in `Rounding`.
 fn new_information_group(
-    i: usize,
+    line: &K4Line,
     currency: Currency,
     amount: Decimal,
-    income: Decimal,
-    costs: Decimal,
-    net_income: Decimal
+    rounding: Rounding,
 ) -> InformationGroup {
-    let mut info_vec = vec![];
+    let net_income = rounding.rounded_net_income;
 
-    info_vec.push(Information { field_code: format!("34{}0", i), field_value: amount.abs().round().to_string() });                  // D.1 Antal/Belopp i utländsk valuta
-    info_vec.push(Information { field_code: format!("34{}1", i), field_value: currency.to_string() });                              // D.1 Beteckning/Valutakod
-    info_vec.push(Information { field_code: format!("34{}2", i), field_value: income.abs().round().to_string() });                  // D.1 Försäljningspris/Återbetalat belopp omräknat till svenska kronor
-    info_vec.push(Information { field_code: format!("34{}3", i), field_value: costs.abs().round().to_string() });                   // D.1 Omkostnadsbelopp/Utlånat belopp omräknat till svenska kronor
+    let mut info_vec = vec![
+        Information { field_code: line.quantity.clone(), field_value: round_kronor(amount.abs()).to_string() },           // D.1 Antal/Belopp i utländsk valuta
+        Information { field_code: line.designation.clone(), field_value: currency.to_string() },                          // D.1 Beteckning/Valutakod
+        Information { field_code: line.sales_price.clone(), field_value: rounding.rounded_income.abs().to_string() },    // D.1 Försäljningspris/Återbetalat belopp omräknat till svenska kronor
+        Information { field_code: line.costs.clone(), field_value: rounding.rounded_costs.abs().to_string() },           // D.1 Omkostnadsbelopp/Utlånat belopp omräknat till svenska kronor
+    ];
 
     match net_income.is_sign_positive() {
-        true => info_vec.push(Information { field_code: format!("34{}4", i), field_value: net_income.abs().round().to_string() }),  // D.1 Vinst
-        false => info_vec.push(Information { field_code: format!("34{}5", i), field_value: net_income.abs().round().to_string() }), // D.1 Förlust
+        true => info_vec.push(Information { field_code: line.gain.clone(), field_value: net_income.abs().to_string() }),  // D.1 Vinst
+        false => info_vec.push(Information { field_code: line.loss.clone(), field_value: net_income.abs().to_string() }), // D.1 Förlust
     }
 
-    info_vec
+    InformationGroup { trade: rounding.trade, information: info_vec }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::calculator::TaxableTrade;
+    use crate::calculator::{MissingCost, Money, TaxableTrade};
     use crate::reader::RevolutRow2023;
     use crate::skatteverket::sru_file::SruFile;
+    use crate::skatteverket::{K4Spec, Taxpayer};
+    use crate::Config;
     use futures::executor::block_on;
     use std::io::Write;
     use std::path::PathBuf;
-    use anyhow::anyhow;
+    use rust_decimal_macros::dec;
 
     #[test]
     fn should_write_sru_file() -> anyhow::Result<()> {
@@ -244,19 +298,23 @@ mod test {
          */
         let taxable_trades = block_on(async {
             let trades = RevolutRow2023::deserialize_from(&PathBuf::from(path)).await?;
-            TaxableTrade::taxable_trades(&trades, &"EOS".to_string(), &"SEK".to_string()).await
+            TaxableTrade::taxable_trades(&trades, &"EOS".to_string(), &"SEK".to_string(), false, MissingCost::Abort).await
         })?;
 
         let taxable_trades = TaxableTrade::try_sum_cash_amount_by_currency(&taxable_trades)?;
 
         let sru_file = SruFile::try_new(
             &taxable_trades,
+            &K4Spec::for_year(2022)?,
             "195001011234".to_string(),
             None
-        ).ok_or(anyhow!(""))?;
+        )?
+            .created_at("2023-04-28T22:20:30".parse()?);
 
         let mut buf = vec![];
-        sru_file.write(&mut buf)?;
+        sru_file.write(&mut buf, false)?;
+        let mut again = vec![];
+        sru_file.write(&mut again, false)?;
 
         /*
          * Then
@@ -265,18 +323,126 @@ mod test {
 
         // let stdout = std::io::stdout();
         // let handle = stdout.lock();
-        // sru_file.write(handle)?;
+        // sru_file.write(handle, false)?;
 
         assert!(output.starts_with("#BLANKETT K4-2022P4\n"));
-        assert!(output.contains("#IDENTITET 195001011234 "));
+        assert!(output.contains("#IDENTITET 195001011234 20230428 222030\n"));
+        assert_eq!(output.as_bytes(), again);
         assert!(output.contains("#UPPGIFT 7014 1\n"));
         assert!(output.contains("#UPPGIFT 3410 105\n"));
         assert!(output.contains("#UPPGIFT 3411 EOS\n"));
         assert!(output.contains("#UPPGIFT 3412 1485\n"));
         assert!(output.contains("#UPPGIFT 3413 2125\n"));
-        assert!(output.contains("#UPPGIFT 3415 639\n"));
+        // The loss of 639.44 is the difference of the rounded sales price and cost
+        assert!(output.contains("#UPPGIFT 3415 640\n"));
+        assert!(output.contains("#UPPGIFT 3500 1485\n#UPPGIFT 3501 2125\n#UPPGIFT 3503 0\n#UPPGIFT 3504 640\n"));
         assert!(output.ends_with("#BLANKETTSLUT\n#FIL_SLUT\n"));
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn should_split_sru_file_between_taxpayers() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let spec = K4Spec::for_year(2023)?;
+        let taxable_trades = vec![
+            TaxableTrade::new(
+                None,
+                "EOS".to_string(),
+                dec!(-55),
+                Money::new_cash("SEK".to_string(), dec!(890.61)),
+                vec![Money::new_cash("SEK".to_string(), dec!(-335.03))],
+                Some(dec!(555.58)),
+            ),
+        ];
+        let taxpayer = |org_num: &str, share| Taxpayer { org_num: org_num.to_string(), name: None, share, path: None };
+        let taxpayers = [taxpayer("195012310123", dec!(0.6)), taxpayer("195501014327", dec!(0.4))];
+        let config = Config { round: true, ..Default::default() };
+
+        /*
+         * When
+         */
+        let sru_file = block_on(TaxableTrade::try_sru_file_of_taxpayers(&taxable_trades, &spec, &taxpayers, &config))?
+            .created_at("2024-04-28T22:20:30".parse()?);
+        let mut buf = vec![];
+        sru_file.write(&mut buf, false)?;
+
+        /*
+         * Then
+         */
+        let output = String::from_utf8(buf)?;
+        // 60% of 890.61 is 534.366 and of 335.03 is 201.018, where 60% of the rounded 891 and 335 is 535 and 201
+        assert!(output.contains("#IDENTITET 195012310123 20240428 222030\n#UPPGIFT 7014 1\n#UPPGIFT 3410 33\n#UPPGIFT 3411 EOS\n#UPPGIFT 3412 534\n#UPPGIFT 3413 201\n#UPPGIFT 3414 333\n"));
+        assert!(output.contains("#IDENTITET 195501014327 20240428 222030\n#UPPGIFT 7014 1\n#UPPGIFT 3410 22\n#UPPGIFT 3411 EOS\n#UPPGIFT 3412 356\n#UPPGIFT 3413 134\n#UPPGIFT 3414 222\n"));
+        assert!(sru_file.validate(&spec).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_write_sru_file_in_latin1() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let sru_file = SruFile::parse("\
+            #BLANKETT K4-2023P4\n\
+            #IDENTITET 195012310123 20240428 222030\n\
+            #NAMN Åsa Öström\n\
+            #UPPGIFT 3410 1\n\
+            #UPPGIFT 3411 EOS\n\
+            #UPPGIFT 3412 20\n\
+            #UPPGIFT 3413 10\n\
+            #UPPGIFT 3414 10\n\
+            #BLANKETTSLUT\n\
+            #FIL_SLUT\n\
+        ")?;
+
+        /*
+         * When
+         */
+        let mut buf = vec![];
+        sru_file.write(&mut buf, false)?;
+
+        /*
+         * Then
+         */
+        assert!(buf.starts_with(b"#BLANKETT K4-2023P4\n#IDENTITET 195012310123 20240428 222030\n#NAMN \xC5sa \xD6str\xF6m\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_not_write_a_name_outside_of_latin1() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let sru_file = SruFile::parse("\
+            #BLANKETT K4-2023P4\n\
+            #IDENTITET 195012310123 20240428 222030\n\
+            #NAMN Łukasz\n\
+            #UPPGIFT 3410 1\n\
+            #UPPGIFT 3411 EOS\n\
+            #UPPGIFT 3412 20\n\
+            #UPPGIFT 3413 10\n\
+            #UPPGIFT 3414 10\n\
+            #BLANKETTSLUT\n\
+            #FIL_SLUT\n\
+        ")?;
+
+        /*
+         * When
+         */
+        let mut buf = vec![];
+        let err = sru_file.write(&mut buf, false).unwrap_err();
+
+        /*
+         * Then
+         */
+        assert_eq!(format!("{:#}", err), "Could not write BLANKETTER.SRU: Line 3: 'Ł' cannot be represented in ISO-8859-1: #NAMN Łukasz");
+        assert!(buf.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDateTime;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use super::sru_file::{Form, Identity, Information, InformationGroup};
+use super::{K4Spec, SruFile};
+
+impl SruFile {
+    /// Reads a BLANKETTER.SRU file with K4 forms, and possibly INK1 forms, in ISO-8859-1 or UTF-8.
+    pub(crate) fn read(path: &PathBuf) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let text = match String::from_utf8(bytes) {
+            Ok(text) => text,
+            // ISO-8859-1 is the first 256 code points of Unicode
+            Err(e) => e.into_bytes().into_iter().map(char::from).collect(),
+        };
+        Self::parse(&text).with_context(|| format!("Could not read SRU file {:?}", path))
+    }
+
+    /// Parses the forms in `text`. The fields of section D are grouped by the line of the
+    /// blankett they belong to, apart from the sums. Other fields come last in a group of their own.
+    pub(crate) fn parse(text: &str) -> Result<Self> {
+        let mut forms = vec![];
+        let mut current: Option<(Form, Vec<Information>)> = None;
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let (label, value) = line.split_once(' ').unwrap_or((line, ""));
+            let value = value.trim();
+            let at = || format!("Line {}: {}", i + 1, line);
+
+            match (label, current.as_mut()) {
+                ("#BLANKETT", None) => {
+                    let form = Form {
+                        form: value.to_string(),
+                        identity: Identity { org_num: String::new(), created: NaiveDateTime::default() },
+                        name: None,
+                        information_groups: vec![],
+                        totals: vec![],
+                        system_info: None,
+                    };
+                    current = Some((form, vec![]));
+                }
+                ("#IDENTITET", Some((form, _))) => {
+                    let (org_num, created) = value.split_once(' ').unwrap_or((value, ""));
+                    form.identity.org_num = org_num.to_string();
+                    form.identity.created = NaiveDateTime::parse_from_str(created.trim(), "%Y%m%d %H%M%S")
+                        .with_context(at)?;
+                }
+                ("#NAMN", Some((form, _))) => form.name = Some(value.to_string()),
+                ("#SYSTEMINFO", Some((form, _))) => form.system_info = Some(value.to_string()),
+                ("#UPPGIFT", Some((_, information))) => {
+                    let (field_code, field_value) = value.split_once(' ').ok_or_else(|| anyhow!("{}: expected a field code and a value", at()))?;
+                    information.push(Information { field_code: field_code.to_string(), field_value: field_value.trim().to_string() });
+                }
+                ("#BLANKETTSLUT", Some(_)) => {
+                    let (form, information) = current.take().unwrap();
+                    forms.push(group_information(form, information)?);
+                }
+                ("#FIL_SLUT", None) => break,
+                ("", _) => {}
+                _ => return Err(anyhow!("{}: unexpected {}", at(), label)),
+            }
+        }
+
+        match current {
+            Some((form, _)) => Err(anyhow!("#BLANKETT {} has no #BLANKETTSLUT", form.form)),
+            None => Ok(SruFile { forms }),
+        }
+    }
+}
+
+fn group_information(mut form: Form, information: Vec<Information>) -> Result<Form> {
+    // Other blanketts, e.g. INK1, keep their fields in one group
+    if !form.is_k4() {
+        form.information_groups = vec![InformationGroup { trade: form.form.clone(), information }];
+        return Ok(form);
+    }
+
+    let spec = K4Spec::for_blankett(&form.form)?;
+
+    let mut lines: BTreeMap<usize, Vec<Information>> = BTreeMap::new();
+    let mut other = vec![];
+    for info in information {
+        match spec.line_of(&info.field_code) {
+            Some(line) => lines.entry(line.line).or_default().push(info),
+            // 7014 numbers the forms and is written by `Form::write`
+            None if info.field_code == "7014" => {}
+            None if spec.totals.field_codes().contains(&info.field_code.as_str()) => form.totals.push(info),
+            None => other.push(info),
+        }
+    }
+
+    form.information_groups = lines.into_iter()
+        .map(|(line, information)| InformationGroup { trade: format!("line {}", line), information })
+        .collect();
+    if !other.is_empty() {
+        form.information_groups.push(InformationGroup { trade: "other fields".to_string(), information: other });
+    }
+
+    Ok(form)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::skatteverket::SruFile;
+
+    #[test]
+    fn should_parse_sru_file() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let text = "\
+            #BLANKETT K4-2022P4\n\
+            #IDENTITET 195012310123 20230428 222030\n\
+            #NAMN Svea Specimen\n\
+            #UPPGIFT 7014 1\n\
+            #UPPGIFT 3410 55\n\
+            #UPPGIFT 3411 EOS\n\
+            #UPPGIFT 3412 891\n\
+            #UPPGIFT 3413 335\n\
+            #UPPGIFT 3414 556\n\
+            #UPPGIFT 3420 2\n\
+            #UPPGIFT 3421 BTC\n\
+            #BLANKETTSLUT\n\
+            #FIL_SLUT\n\
+        ";
+
+        /*
+         * When
+         */
+        let sru_file = SruFile::parse(text)?;
+
+        /*
+         * Then
+         */
+        assert_eq!(sru_file.forms.len(), 1);
+        let form = &sru_file.forms[0];
+        assert_eq!(form.form, "K4-2022P4");
+        assert_eq!(form.identity.org_num, "195012310123");
+        assert_eq!(form.name.as_deref(), Some("Svea Specimen"));
+        assert_eq!(form.information_groups.len(), 2);
+        assert_eq!(form.information_groups[0].information.len(), 5);
+        assert_eq!(form.information_groups[1].information[1].field_value, "BTC");
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_require_the_end_of_a_form() {
+        /*
+         * Given
+         */
+        let text = "\
+            #BLANKETT K4-2022P4\n\
+            #IDENTITET 195012310123 20230428 222030\n\
+            #UPPGIFT 3410 55\n\
+        ";
+
+        /*
+         * When
+         */
+        let sru_file = SruFile::parse(text);
+
+        /*
+         * Then
+         */
+        assert_eq!(sru_file.unwrap_err().to_string(), "#BLANKETT K4-2022P4 has no #BLANKETTSLUT");
+    }
+}
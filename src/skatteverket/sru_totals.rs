@@ -0,0 +1,242 @@
+use anyhow::{anyhow, Result};
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use super::sru_file::{Form, Information};
+use super::{K4Spec, K4Totals, SruFile};
+
+/// The sums of section D of a K4 form, or of all forms in an SRU file.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub(crate) struct SruTotals {
+    #[serde(rename = "Form")]
+    pub(crate) form: String,
+
+    #[serde(rename = "Sales Price")]
+    pub(crate) sales_price: Decimal,
+
+    #[serde(rename = "Costs")]
+    pub(crate) costs: Decimal,
+
+    #[serde(rename = "Gain")]
+    pub(crate) gain: Decimal,
+
+    #[serde(rename = "Loss")]
+    pub(crate) loss: Decimal,
+}
+
+impl SruTotals {
+    /// The fields of the sums, with the field codes in `codes`.
+    pub(crate) fn to_information(&self, codes: &K4Totals) -> Vec<Information> {
+        [
+            (&codes.sales_price, self.sales_price),
+            (&codes.costs, self.costs),
+            (&codes.gain, self.gain),
+            (&codes.loss, self.loss),
+        ]
+            .into_iter()
+            .map(|(field_code, value)| Information { field_code: field_code.clone(), field_value: value.to_string() })
+            .collect()
+    }
+}
+
+impl SruFile {
+    /// Returns the sums of every K4 form, followed by the sums of all K4 forms in the file.
+    pub(crate) fn totals(&self, spec: &K4Spec) -> Result<Vec<SruTotals>> {
+        let mut totals: Vec<SruTotals> = self.forms.iter().filter(|form| form.is_k4()).enumerate()
+            .map(|(i, form)| Ok(SruTotals { form: (i + 1).to_string(), ..form.sums(spec)? }))
+            .collect::<Result<_>>()?;
+
+        let total = totals.iter()
+            .fold(SruTotals { form: "Total".to_string(), ..Default::default() }, |acc, t|
+                SruTotals {
+                    sales_price: acc.sales_price + t.sales_price,
+                    costs: acc.costs + t.costs,
+                    gain: acc.gain + t.gain,
+                    loss: acc.loss + t.loss,
+                    ..acc
+                }
+            );
+        totals.push(total);
+
+        Ok(totals)
+    }
+}
+
+impl Form {
+    /// Adds up the sales prices, costs, gains and losses on the lines of section D. Fails if one
+    /// of them is not a number.
+    pub(crate) fn sums(&self, spec: &K4Spec) -> Result<SruTotals> {
+        let mut sums = SruTotals::default();
+
+        for info in self.information_groups.iter().flat_map(|g| &g.information) {
+            if let Some(line) = spec.line_of(&info.field_code) {
+                let sum = match info.field_code.as_str() {
+                    code if code == line.sales_price => &mut sums.sales_price,
+                    code if code == line.costs => &mut sums.costs,
+                    code if code == line.gain => &mut sums.gain,
+                    code if code == line.loss => &mut sums.loss,
+                    _ => continue,
+                };
+                let value: Decimal = info.field_value.parse()
+                    .map_err(|_| anyhow!("Could not add up {}: field {} is not a number, got '{}'", self.form, info.field_code, info.field_value))?;
+                *sum += value;
+            }
+        }
+
+        Ok(sums)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::skatteverket::{K4Spec, SruFile};
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn should_sum_gains_and_losses_on_lines_of_form() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let spec = K4Spec::for_year(2022)?;
+        let sru_file = SruFile::parse("\
+            #BLANKETT K4-2022P4\n\
+            #IDENTITET 195012310123 20230428 222030\n\
+            #UPPGIFT 3410 55\n\
+            #UPPGIFT 3411 EOS\n\
+            #UPPGIFT 3412 891\n\
+            #UPPGIFT 3413 335\n\
+            #UPPGIFT 3414 556\n\
+            #UPPGIFT 3420 2\n\
+            #UPPGIFT 3421 BTC\n\
+            #UPPGIFT 3422 100\n\
+            #UPPGIFT 3423 150\n\
+            #UPPGIFT 3425 50\n\
+            #BLANKETTSLUT\n\
+            #FIL_SLUT\n\
+        ")?;
+
+        /*
+         * When
+         */
+        let sums = sru_file.forms[0].sums(&spec)?;
+
+        /*
+         * Then
+         */
+        assert_eq!((sums.sales_price, sums.costs, sums.gain, sums.loss), (dec!(991), dec!(485), dec!(556), dec!(50)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_not_add_up_a_value_that_is_not_a_number() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let spec = K4Spec::for_year(2022)?;
+        let sru_file = SruFile::parse("\
+            #BLANKETT K4-2022P4\n\
+            #IDENTITET 195012310123 20230428 222030\n\
+            #UPPGIFT 3410 55\n\
+            #UPPGIFT 3411 EOS\n\
+            #UPPGIFT 3412 89l\n\
+            #UPPGIFT 3413 335\n\
+            #UPPGIFT 3414 556\n\
+            #BLANKETTSLUT\n\
+            #FIL_SLUT\n\
+        ")?;
+
+        /*
+         * When
+         */
+        let err = sru_file.forms[0].sums(&spec).unwrap_err();
+
+        /*
+         * Then
+         */
+        assert_eq!(err.to_string(), "Could not add up K4-2022P4: field 3412 is not a number, got '89l'");
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_total_every_form() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let spec = K4Spec::for_year(2022)?;
+        let sru_file = SruFile::parse("\
+            #BLANKETT K4-2022P4\n\
+            #IDENTITET 195012310123 20230428 222030\n\
+            #UPPGIFT 3410 55\n\
+            #UPPGIFT 3411 EOS\n\
+            #UPPGIFT 3412 891\n\
+            #UPPGIFT 3413 335\n\
+            #UPPGIFT 3414 556\n\
+            #BLANKETTSLUT\n\
+            #BLANKETT K4-2022P4\n\
+            #IDENTITET 195012310123 20230428 222030\n\
+            #UPPGIFT 3410 1\n\
+            #UPPGIFT 3411 ETH\n\
+            #UPPGIFT 3412 20\n\
+            #UPPGIFT 3413 30\n\
+            #UPPGIFT 3415 10\n\
+            #BLANKETTSLUT\n\
+            #FIL_SLUT\n\
+        ")?;
+
+        /*
+         * When
+         */
+        let totals = sru_file.totals(&spec)?;
+
+        /*
+         * Then
+         */
+        let totals: Vec<_> = totals.iter().map(|t| (t.form.as_str(), t.sales_price, t.costs, t.gain, t.loss)).collect();
+        assert_eq!(totals, vec![
+            ("1", dec!(891), dec!(335), dec!(556), dec!(0)),
+            ("2", dec!(20), dec!(30), dec!(0), dec!(10)),
+            ("Total", dec!(911), dec!(365), dec!(556), dec!(10)),
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_leave_ink1_out_of_totals() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let spec = K4Spec::for_year(2023)?;
+        let sru_file = SruFile::parse("\
+            #BLANKETT K4-2023P4\n\
+            #IDENTITET 195012310123 20240428 222030\n\
+            #UPPGIFT 3410 1\n\
+            #UPPGIFT 3411 ETH\n\
+            #UPPGIFT 3412 20\n\
+            #UPPGIFT 3413 10\n\
+            #UPPGIFT 3414 10\n\
+            #BLANKETTSLUT\n\
+            #BLANKETT INK1-2023P1\n\
+            #IDENTITET 195012310123 20240428 222030\n\
+            #UPPGIFT 1103 10\n\
+            #BLANKETTSLUT\n\
+            #FIL_SLUT\n\
+        ")?;
+
+        /*
+         * When
+         */
+        let totals = sru_file.totals(&spec)?;
+
+        /*
+         * Then
+         */
+        let forms: Vec<&str> = totals.iter().map(|t| t.form.as_str()).collect();
+        assert_eq!(forms, vec!["1", "Total"]);
+        assert_eq!(totals[1].gain, dec!(10));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+/// Describes what produced an SRU file, for its #SYSTEMINFO: the version of revolutax, the
+/// SHA-256 of every input file and the flags that change the figures.
+pub(crate) fn system_info(inputs: &[&PathBuf], options: &[String]) -> Result<String> {
+    let mut info = vec![format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))];
+
+    for path in inputs {
+        let bytes = std::fs::read(path).with_context(|| format!("Could not read {:?}", path))?;
+        let hash: String = Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect();
+        let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+        info.push(format!("{} sha256:{}", name, hash));
+    }
+
+    if !options.is_empty() {
+        info.push(options.join(" "));
+    }
+
+    Ok(info.join("; "))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::skatteverket::system_info;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    #[test]
+    fn should_describe_inputs() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let mut file = tempfile::NamedTempFile::new()?;
+        write!(file, "abc")?;
+        let path = PathBuf::from(file.path());
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+
+        /*
+         * When
+         */
+        let info = system_info(&[&path], &["--sru-file".to_string(), "--sum".to_string()])?;
+
+        /*
+         * Then
+         */
+        assert_eq!(info, format!(
+            "revolutax {}; {} sha256:ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad; --sru-file --sum",
+            env!("CARGO_PKG_VERSION"), name
+        ));
+
+        Ok(())
+    }
+}
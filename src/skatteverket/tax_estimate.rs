@@ -0,0 +1,185 @@
+/*
+ * https://www.skatteverket.se/privat/skatter/vardepapper/andratillgangar/kryptovalutor.4.15532c7b1442f256bae11b60.html
+ * https://www.skatteverket.se/privat/skatter/arbeteochinkomst/askattsomgarfor/skattpakapital.4.18e1b10334ebe8bc80003386.html
+ */
+
+use anyhow::{anyhow, Result};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::Serialize;
+
+use crate::calculator::TaxableTrade;
+use super::{round_kronor, Rounding};
+
+// Skatt på överskott i inkomstslaget kapital
+const TAX_RATE: Decimal = dec!(0.30);
+
+// Förlust på andra tillgångar än marknadsnoterade aktier m.m. dras av till 70 %
+const DEDUCTIBLE_LOSS_RATE: Decimal = dec!(0.70);
+
+// Skattereduktion för underskott av kapital: 30 % av underskottet upp till 100 000 kr, 21 % av resten
+const DEFICIT_REDUCTION_RATE: Decimal = dec!(0.30);
+const DEFICIT_REDUCTION_LIMIT: Decimal = dec!(100000);
+const DEFICIT_REDUCTION_RATE_ABOVE_LIMIT: Decimal = dec!(0.21);
+
+/// An estimate of the tax on the capital income from the taxable trades,
+/// together with the figures that are reported on INK1.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct TaxEstimate {
+    // INK1 7.4 Vinst vid försäljning av andra tillgångar (K4 sektion D)
+    #[serde(rename = "Gains (INK1 7.4)")]
+    pub(crate) gains: Decimal,
+
+    #[serde(rename = "Losses")]
+    pub(crate) losses: Decimal,
+
+    // INK1 8.3 Förlust vid försäljning av andra tillgångar, 70 % av förlusten
+    #[serde(rename = "Deductible Losses (INK1 8.3)")]
+    pub(crate) deductible_losses: Decimal,
+
+    #[serde(rename = "Other Capital Income")]
+    pub(crate) other_capital_income: Decimal,
+
+    // Överskott (positive) eller underskott (negative) av kapital
+    #[serde(rename = "Capital Income")]
+    pub(crate) capital_income: Decimal,
+
+    // Positive is tax to pay, negative is a tax reduction
+    #[serde(rename = "Tax")]
+    pub(crate) tax: Decimal,
+}
+
+/// 70 % of `losses`, in whole kronor, as in box 8.3 of INK1.
+pub(crate) fn deductible_losses(losses: Decimal) -> Decimal {
+    round_kronor(losses * DEDUCTIBLE_LOSS_RATE)
+}
+
+impl TaxEstimate {
+    /// Estimates the tax from the net income of the taxable trades and the taxpayer's other
+    /// capital income (negative for a loss). The net income of every trade is the difference of
+    /// its income and cost in whole kronor, as on K4, see `Rounding`.
+    pub(crate) fn try_new(taxable_trades: &[TaxableTrade], other_capital_income: Decimal) -> Result<Self> {
+        let net_incomes =
+            taxable_trades.iter()
+                .map(|trade|
+                    Rounding::try_new(trade)
+                        .map(|rounding| rounding.rounded_net_income)
+                        .ok_or(anyhow!("Could not estimate the tax: the net income of {} {} is not in the base currency", trade.amount, trade.currency))
+                )
+                .collect::<Result<Vec<Decimal>>>()?;
+
+        Ok(TaxEstimate::new(&net_incomes, other_capital_income))
+    }
+
+    /// Estimates the tax from net incomes in whole kronor.
+    pub(crate) fn new(net_incomes: &[Decimal], other_capital_income: Decimal) -> Self {
+        let mut gains = dec!(0);
+        let mut losses = dec!(0);
+
+        for net_income in net_incomes {
+            match net_income.is_sign_positive() {
+                true => gains += net_income,
+                false => losses += net_income.abs(),
+            }
+        }
+
+        let deductible_losses = deductible_losses(losses);
+        let capital_income = gains - deductible_losses + other_capital_income;
+
+        let tax = match capital_income.is_sign_positive() {
+            true => capital_income * TAX_RATE,
+            false => {
+                let deficit = capital_income.abs();
+                let reduction =
+                    deficit.min(DEFICIT_REDUCTION_LIMIT) * DEFICIT_REDUCTION_RATE
+                        + (deficit - DEFICIT_REDUCTION_LIMIT).max(dec!(0)) * DEFICIT_REDUCTION_RATE_ABOVE_LIMIT;
+                -reduction
+            }
+        };
+
+        TaxEstimate {
+            gains,
+            losses,
+            deductible_losses,
+            other_capital_income,
+            capital_income,
+            tax: round_kronor(tax),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::calculator::{Money, TaxableTrade};
+    use crate::skatteverket::TaxEstimate;
+    use rust_decimal_macros::dec;
+    use std::error::Error;
+
+    #[test]
+    fn should_estimate_tax() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let trade = |net_income| TaxableTrade::new(
+            None,
+            "EOS".to_string(),
+            dec!(-1),
+            Money::new_cash("SEK".to_string(), net_income),
+            vec![],
+            Some(net_income)
+        );
+        let taxable_trades = vec![trade(dec!(10000)), trade(dec!(-150000)), trade(dec!(-50000))];
+
+        /*
+         * When
+         */
+        let deficit = TaxEstimate::try_new(&taxable_trades, dec!(0))?;
+        let surplus = TaxEstimate::try_new(&taxable_trades, dec!(200000))?;
+
+        /*
+         * Then
+         */
+        assert_eq!(deficit.gains, dec!(10000));
+        assert_eq!(deficit.losses, dec!(200000));
+        assert_eq!(deficit.deductible_losses, dec!(140000));
+        assert_eq!(deficit.capital_income, dec!(-130000));
+        assert_eq!(deficit.tax, dec!(-36300));
+
+        assert_eq!(surplus.capital_income, dec!(70000));
+        assert_eq!(surplus.tax, dec!(21000));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_round_halves_away_from_zero_as_on_k4_and_ink1() -> Result<(), Box<dyn Error>> {
+        /*
+         * Given
+         */
+        let taxable_trades = vec![
+            TaxableTrade::new(
+                None,
+                "EOS".to_string(),
+                dec!(-1),
+                Money::new_cash("SEK".to_string(), dec!(100.5)),
+                vec![Money::new_cash("SEK".to_string(), dec!(-115.5))],
+                Some(dec!(-15))
+            ),
+        ];
+
+        /*
+         * When
+         */
+        let estimate = TaxEstimate::try_new(&taxable_trades, dec!(0))?;
+
+        /*
+         * Then
+         */
+        assert_eq!(estimate.losses, dec!(15));
+        assert_eq!(estimate.deductible_losses, dec!(11));
+        assert_eq!(estimate.capital_income, dec!(-11));
+        assert_eq!(estimate.tax, dec!(-3));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+use anyhow::{anyhow, Context, Result};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// A taxpayer in a joint SRU file, given as `ORGNUM,NAME,SHARE[,PATH]`, e.g.
+/// `195012310123,Svea Specimen,50%`. The taxpayer reports `SHARE` of the taxable trades in the
+/// statement at `PATH`, or in the statement of the command line when no `PATH` is given.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Taxpayer {
+    pub(crate) org_num: String,
+    pub(crate) name: Option<String>,
+    pub(crate) share: Decimal,
+    pub(crate) path: Option<PathBuf>,
+}
+
+impl FromStr for Taxpayer {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let fields: Vec<&str> = s.split(',').map(str::trim).collect();
+        let (org_num, name, share, path) =
+            match fields[..] {
+                [org_num, name, share] => (org_num, name, share, None),
+                [org_num, name, share, path] => (org_num, name, share, Some(PathBuf::from(path))),
+                _ => return Err(anyhow!("expected ORGNUM,NAME,SHARE[,PATH]")),
+            };
+
+        let share = match share.strip_suffix('%') {
+            Some(percent) => Decimal::from_str(percent.trim()).context("Invalid SHARE")? / dec!(100),
+            None => Decimal::from_str(share).context("Invalid SHARE")?,
+        };
+        if share <= dec!(0) || share > dec!(1) {
+            return Err(anyhow!("SHARE must be above 0 and at most 1 (100%), got {}", share));
+        }
+
+        Ok(Taxpayer {
+            org_num: org_num.to_string(),
+            name: Some(name.to_string()).filter(|name| !name.is_empty()),
+            share: share.normalize(),
+            path,
+        })
+    }
+}
+
+impl Taxpayer {
+    pub fn org_num(&self) -> &str {
+        &self.org_num
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::skatteverket::Taxpayer;
+    use rust_decimal_macros::dec;
+    use std::path::PathBuf;
+
+    #[test]
+    fn should_parse_taxpayer() -> anyhow::Result<()> {
+        /*
+         * When
+         */
+        let joint: Taxpayer = "195012310123,Svea Specimen,50%".parse()?;
+        let own: Taxpayer = "195012310123,,1,revolut-2023.csv".parse()?;
+
+        /*
+         * Then
+         */
+        assert_eq!(joint, Taxpayer {
+            org_num: "195012310123".to_string(),
+            name: Some("Svea Specimen".to_string()),
+            share: dec!(0.5),
+            path: None,
+        });
+        assert_eq!(own.name, None);
+        assert_eq!(own.path, Some(PathBuf::from("revolut-2023.csv")));
+        assert!("195012310123,Svea Specimen,150%".parse::<Taxpayer>().is_err());
+        assert!("195012310123".parse::<Taxpayer>().is_err());
+
+        Ok(())
+    }
+}
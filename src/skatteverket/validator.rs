@@ -0,0 +1,365 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+
+use super::{K4Spec, SruFile};
+
+// #NAMN is at most 250 characters long
+const MAX_NAME_LEN: usize = 250;
+
+// Numeric fields are whole kronor or quantities of at most 12 digits
+const MAX_NUMERIC_VALUE: u64 = 999_999_999_999;
+
+impl SruFile {
+    /// Checks the forms against Skatteverket's rules for section D of K4 in `spec`, and lists
+    /// every error with the taxable trade that caused it. The sums must add up the lines.
+    /// Other blanketts, e.g. INK1, are carried over from the K4 forms and not checked.
+    pub(crate) fn validate(&self, spec: &K4Spec) -> Result<()> {
+        let mut errors = vec![];
+
+        for (i, form) in self.forms.iter().filter(|form| form.is_k4()).enumerate() {
+            let form_num = i + 1;
+
+            if let Some(e) = org_num_error(&form.identity.org_num) {
+                errors.push(format!("K4 form {}: #IDENTITET {}", form_num, e));
+            }
+
+            if let Some(name) = &form.name {
+                if name.chars().count() > MAX_NAME_LEN {
+                    errors.push(format!("K4 form {}: #NAMN is longer than {} characters", form_num, MAX_NAME_LEN));
+                }
+            }
+
+            if form.information_groups.len() > spec.lines.len() {
+                errors.push(format!(
+                    "K4 form {}: {} lines in section D, at most {} are allowed",
+                    form_num, form.information_groups.len(), spec.lines.len()
+                ));
+            }
+
+            // 7014 numbers the forms and is written by `Form::write`
+            let mut field_codes: HashSet<&str> = HashSet::from(["7014"]);
+
+            for group in &form.information_groups {
+                // A group is on the line of its first field
+                let line = match group.information.first().map(|info| (info, spec.line_of(&info.field_code))) {
+                    Some((_, Some(line))) => line,
+                    Some((info, None)) => {
+                        errors.push(format!("K4 form {} ({}): field code {} is not on a line of section D", form_num, group.trade, info.field_code));
+                        continue;
+                    }
+                    None => continue,
+                };
+                let at = format!("K4 form {}, line {} ({})", form_num, line.line, group.trade);
+
+                for info in &group.information {
+                    if !field_codes.insert(&info.field_code) {
+                        errors.push(format!("{}: field code {} is repeated", at, info.field_code));
+                    }
+
+                    if !line.field_codes().contains(&info.field_code.as_str()) {
+                        errors.push(format!("{}: field code {} is not on line {}", at, info.field_code, line.line));
+                        continue;
+                    }
+
+                    if info.field_code == line.designation {
+                        if info.field_value.trim().is_empty() {
+                            errors.push(format!("{}: field {} is empty", at, info.field_code));
+                        }
+                    } else if !info.field_value.parse::<u64>().is_ok_and(|v| v <= MAX_NUMERIC_VALUE) {
+                        errors.push(format!(
+                            "{}: field {} must be an integer between 0 and {}, got '{}'",
+                            at, info.field_code, MAX_NUMERIC_VALUE, info.field_value
+                        ));
+                    }
+                }
+
+                let gains_and_losses = group.information.iter()
+                    .filter(|info| info.field_code == line.gain || info.field_code == line.loss)
+                    .count();
+                if gains_and_losses != 1 {
+                    errors.push(format!("{}: expected either a gain ({}) or a loss ({})", at, line.gain, line.loss));
+                }
+            }
+
+            let sums = match form.sums(spec) {
+                Ok(sums) => sums.to_information(&spec.totals),
+                Err(err) => {
+                    errors.push(format!("K4 form {}: {}", form_num, err));
+                    vec![]
+                }
+            };
+            for info in &form.totals {
+                if !field_codes.insert(&info.field_code) {
+                    errors.push(format!("K4 form {}: field code {} is repeated", form_num, info.field_code));
+                }
+                if let Some(sum) = sums.iter().find(|sum| sum.field_code == info.field_code) {
+                    if sum.field_value != info.field_value {
+                        errors.push(format!(
+                            "K4 form {}: field {} is {}, but the lines add up to {}",
+                            form_num, info.field_code, info.field_value, sum.field_value
+                        ));
+                    }
+                }
+            }
+        }
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(anyhow!("The SRU file has {} errors:\n{}", errors.len(), errors.join("\n"))),
+        }
+    }
+}
+
+/// Returns why `org_num` is not a personnummer or organisationsnummer in the form SSÅÅMMDDNNNK.
+fn org_num_error(org_num: &str) -> Option<String> {
+    if org_num.len() != 12 || !org_num.chars().all(|c| c.is_ascii_digit()) {
+        return Some(format!("{} must be 12 digits in the form SSÅÅMMDDNNNK", org_num));
+    }
+
+    // Luhn algorithm over ÅÅMMDDNNNK
+    let sum: u32 = org_num[2..].chars()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { d * 2 } else { d })
+        .map(|d| d / 10 + d % 10)
+        .sum();
+
+    match sum % 10 {
+        0 => None,
+        _ => Some(format!("{} has an invalid check digit", org_num)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::skatteverket::sru_file::Information;
+    use crate::skatteverket::{K4Spec, SruFile};
+
+    const VALID: &str = "\
+        #BLANKETT K4-2023P4\n\
+        #IDENTITET 195012310123 20240428 222030\n\
+        #NAMN Svea Specimen\n\
+        #UPPGIFT 3410 105\n\
+        #UPPGIFT 3411 EOS\n\
+        #UPPGIFT 3412 1485\n\
+        #UPPGIFT 3413 2125\n\
+        #UPPGIFT 3415 640\n\
+        #UPPGIFT 3500 1485\n\
+        #UPPGIFT 3501 2125\n\
+        #UPPGIFT 3503 0\n\
+        #UPPGIFT 3504 640\n\
+        #BLANKETTSLUT\n\
+        #FIL_SLUT\n\
+    ";
+
+    /// Parses `sru_file` and returns the errors of `SruFile::validate`, one per rule broken.
+    fn errors_of(sru_file: &str) -> anyhow::Result<Vec<String>> {
+        let spec = K4Spec::for_year(2023)?;
+        let errors = match SruFile::parse(sru_file)?.validate(&spec) {
+            Ok(()) => vec![],
+            Err(e) => e.to_string().lines().skip(1).map(String::from).collect(),
+        };
+        Ok(errors)
+    }
+
+    #[test]
+    fn should_accept_valid_form() -> anyhow::Result<()> {
+        /*
+         * When
+         */
+        let errors = errors_of(VALID)?;
+
+        /*
+         * Then
+         */
+        assert_eq!(errors, Vec::<String>::new());
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_check_luhn_digit_of_org_num() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let sru_file = VALID.replace("195012310123", "195001011234");
+
+        /*
+         * When
+         */
+        let errors = errors_of(&sru_file)?;
+
+        /*
+         * Then
+         */
+        assert_eq!(errors, ["K4 form 1: #IDENTITET 195001011234 has an invalid check digit"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_require_12_digits_in_org_num() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let sru_file = VALID.replace("195012310123", "5012310123");
+
+        /*
+         * When
+         */
+        let errors = errors_of(&sru_file)?;
+
+        /*
+         * Then
+         */
+        assert_eq!(errors, ["K4 form 1: #IDENTITET 5012310123 must be 12 digits in the form SSÅÅMMDDNNNK"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_limit_length_of_name() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let longest = VALID.replace("Svea Specimen", &"Å".repeat(250));
+        let too_long = VALID.replace("Svea Specimen", &"Å".repeat(251));
+
+        /*
+         * When
+         */
+        let longest = errors_of(&longest)?;
+        let too_long = errors_of(&too_long)?;
+
+        /*
+         * Then
+         */
+        assert_eq!(longest, Vec::<String>::new());
+        assert_eq!(too_long, ["K4 form 1: #NAMN is longer than 250 characters"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_not_repeat_field_codes() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let sru_file = VALID.replace("#UPPGIFT 3411 EOS\n", "#UPPGIFT 3411 EOS\n#UPPGIFT 3411 EOS\n");
+
+        /*
+         * When
+         */
+        let errors = errors_of(&sru_file)?;
+
+        /*
+         * Then
+         */
+        assert_eq!(errors, ["K4 form 1, line 1 (line 1): field code 3411 is repeated"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_require_integers_in_numeric_fields() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let sru_file = VALID.replace("#UPPGIFT 3410 105\n", "#UPPGIFT 3410 105.5\n");
+
+        /*
+         * When
+         */
+        let errors = errors_of(&sru_file)?;
+
+        /*
+         * Then
+         */
+        assert_eq!(errors, ["K4 form 1, line 1 (line 1): field 3410 must be an integer between 0 and 999999999999, got '105.5'"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_require_either_gain_or_loss() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let sru_file = VALID.replace("#UPPGIFT 3415 640\n", "#UPPGIFT 3414 0\n#UPPGIFT 3415 640\n");
+
+        /*
+         * When
+         */
+        let errors = errors_of(&sru_file)?;
+
+        /*
+         * Then
+         */
+        assert_eq!(errors, ["K4 form 1, line 1 (line 1): expected either a gain (3414) or a loss (3415)"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_check_sums_against_lines() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let sru_file = VALID.replace("#UPPGIFT 3501 2125\n", "#UPPGIFT 3501 2000\n");
+
+        /*
+         * When
+         */
+        let errors = errors_of(&sru_file)?;
+
+        /*
+         * Then
+         */
+        assert_eq!(errors, ["K4 form 1: field 3501 is 2000, but the lines add up to 2125"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_check_a_form_that_starts_on_line_2_against_line_2() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let sru_file = VALID.replace("#UPPGIFT 341", "#UPPGIFT 342");
+
+        /*
+         * When
+         */
+        let errors = errors_of(&sru_file)?;
+
+        /*
+         * Then
+         */
+        assert_eq!(errors, Vec::<String>::new());
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_require_field_codes_of_the_line() -> anyhow::Result<()> {
+        /*
+         * Given
+         */
+        let spec = K4Spec::for_year(2023)?;
+        let mut sru_file = SruFile::parse(VALID)?;
+        sru_file.forms[0].information_groups[0].information.push(Information { field_code: "3424".to_string(), field_value: "0".to_string() });
+
+        /*
+         * When
+         */
+        let err = sru_file.validate(&spec).unwrap_err();
+
+        /*
+         * Then
+         */
+        let errors: Vec<String> = err.to_string().lines().skip(1).map(String::from).collect();
+        assert_eq!(errors, vec!["K4 form 1, line 1 (line 1): field code 3424 is not on line 1"]);
+
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
 /// Wraps the `stdout.lock()` in a `csv::Writer` and writes the rows.
 /// The `csv::Writer` is already buffered so there is no need to wrap
 /// `stdout.lock()` in a `io::BufWriter`.
-pub(crate) async fn print_csv_rows<S: serde::Serialize>(rows: &Vec<S>) -> std::io::Result<()>{
+pub(crate) async fn print_csv_rows<S: serde::Serialize>(rows: &[S]) -> std::io::Result<()>{
     let stdout = std::io::stdout();
     let lock = stdout.lock();
     let mut wtr =
@@ -20,4 +20,60 @@ pub(crate) async fn print_csv_rows<S: serde::Serialize>(rows: &Vec<S>) -> std::i
     );
     err.map_or(Ok(()), Err)?;
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Writes the header and the records as csv to `stdout`, like `print_csv_rows`.
+pub(crate) async fn print_records(header: &[String], records: &[Vec<String>]) -> std::io::Result<()> {
+    let stdout = std::io::stdout();
+    let lock = stdout.lock();
+    let mut wtr =
+        csv::WriterBuilder::new()
+            .delimiter(b';')
+            .from_writer(lock);
+
+    wtr.write_record(header)?;
+    for record in records {
+        wtr.write_record(record)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Writes the header and the records to `stdout` as a table with aligned columns.
+/// Numbers are aligned to the right.
+pub(crate) fn print_table(header: &[String], records: &[Vec<String>]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let widths: Vec<usize> =
+        header.iter()
+            .enumerate()
+            .map(|(i, h)|
+                records.iter()
+                    .filter_map(|r| r.get(i))
+                    .map(|cell| cell.chars().count())
+                    .fold(h.chars().count(), usize::max)
+            )
+            .collect();
+
+    let format_row = |row: &[String]| -> String {
+        row.iter()
+            .zip(&widths)
+            .map(|(cell, width)| match cell.parse::<rust_decimal::Decimal>() {
+                Ok(_) => format!("{:>width$}", cell, width = width),
+                Err(_) => format!("{:<width$}", cell, width = width),
+            })
+            .collect::<Vec<String>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    let stdout = std::io::stdout();
+    let mut lock = stdout.lock();
+    writeln!(lock, "{}", format_row(header))?;
+    writeln!(lock, "{}", widths.iter().map(|w| "-".repeat(*w)).collect::<Vec<String>>().join("  "))?;
+    for record in records {
+        writeln!(lock, "{}", format_row(record))?;
+    }
+    Ok(())
+}